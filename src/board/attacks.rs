@@ -1,41 +1,55 @@
-use super::bitboard::{BitBoard, Square, FILEA, FILEH, RANK1, RANK8};
+use super::{
+    bitboard::{BitBoard, Square},
+    magic::{bishop_attacks, rook_attacks},
+    Color,
+};
 
 pub const KNIGHT_ATTACKS: [BitBoard; 64] = gen_knight_attacks();
 pub const KING_ATTACKS: [BitBoard; 64] = gen_king_attacks();
+/// Pawn captures indexed by `[color][square]`
+pub const PAWN_ATTACKS: [[BitBoard; 64]; 2] = [
+    gen_pawn_attacks(Color::White),
+    gen_pawn_attacks(Color::Black),
+];
 
 /// Get all moves for a king on `square`
 ///
 /// `occ`, occupancy, should be a [Bitboard] of all pieces on the board.
 pub fn get_king_attacks(square: Square, occ: BitBoard) -> BitBoard {
-    todo!()
+    KING_ATTACKS[square as usize]
 }
 
 /// Get all moves for a queen on `square`
 ///
 /// `occ`, occupancy, should be a [Bitboard] of all pieces on the board.
 pub fn get_queen_attacks(square: Square, occ: BitBoard) -> BitBoard {
-    todo!()
+    get_rook_attacks(square, occ) | get_bishop_attacks(square, occ)
 }
 
 /// Get all moves for a rook on `square`
 ///
 /// `occ`, occupancy, should be a [Bitboard] of all pieces on the board.
 pub fn get_rook_attacks(square: Square, occ: BitBoard) -> BitBoard {
-    todo!()
+    rook_attacks(square, occ)
 }
 
 /// Get all moves for a bishop on `square`
 ///
 /// `occ`, occupancy, should be a [Bitboard] of all pieces on the board.
 pub fn get_bishop_attacks(square: Square, occ: BitBoard) -> BitBoard {
-    todo!()
+    bishop_attacks(square, occ)
 }
 
 /// Get all moves for a knight on `square`
 ///
 /// `occ`, occupancy, should be a [Bitboard] of all pieces on the board.
 pub fn get_knight_attacks(square: Square, occ: BitBoard) -> BitBoard {
-    todo!()
+    KNIGHT_ATTACKS[square as usize]
+}
+
+/// Get the squares a pawn of `color` on `square` attacks diagonally
+pub fn get_pawn_attacks(square: Square, color: Color) -> BitBoard {
+    PAWN_ATTACKS[color as usize][square as usize]
 }
 
 const fn gen_knight_attacks() -> [BitBoard; 64] {
@@ -43,13 +57,13 @@ const fn gen_knight_attacks() -> [BitBoard; 64] {
 
     let mut i = 0;
     while i < 64 {
-        let knights = 1 << i;
+        let knights = BitBoard::new(1 << i);
         // This is an implementation based of an algorithm presented on the chessprogramming wiki
         // https://www.chessprogramming.org/Knight_Pattern
-        let l1 = (knights >> 1) & (0x7f7f7f7f7f7f7f7f);
-        let l2 = (knights >> 2) & (0x3f3f3f3f3f3f3f3f);
-        let r1 = (knights << 1) & (0xfefefefefefefefe);
-        let r2 = (knights << 2) & (0xfcfcfcfcfcfcfcfc);
+        let l1 = knights.west().bits();
+        let l2 = knights.west().west().bits();
+        let r1 = knights.east().bits();
+        let r2 = knights.east().east().bits();
         let h1 = l1 | r1;
         let h2 = l2 | r2;
         let board = (h1 << 16) | (h1 >> 16) | (h2 << 8) | (h2 >> 8);
@@ -65,19 +79,183 @@ const fn gen_king_attacks() -> [BitBoard; 64] {
 
     let mut i = 0;
     while i < 64 {
-        let king = 1 << i;
-        let mut moves = 0;
-        moves ^= (king << 1) & !FILEH;
-        moves ^= (king >> 1) & !FILEA;
-        moves ^= (king << 8) & !RANK1;
-        moves ^= (king >> 8) & !RANK8;
-        moves ^= (king << 9) & !(RANK1 | FILEH);
-        moves ^= (king << 7) & !(RANK1 | FILEA);
-        moves ^= (king >> 9) & !(RANK8 | FILEA);
-        moves ^= (king >> 7) & !(RANK8 | FILEH);
+        let king = BitBoard::new(1 << i);
+        let row = king.bits() | king.east().bits() | king.west().bits();
+        let moves = (row << 8) | (row >> 8) | (row & !king.bits());
         boards[i] = BitBoard::new(moves);
         i += 1;
     }
 
     boards
 }
+
+const fn gen_pawn_attacks(color: Color) -> [BitBoard; 64] {
+    let mut boards: [BitBoard; 64] = [BitBoard::new(0); 64];
+
+    let mut i = 0;
+    while i < 64 {
+        let pawn = BitBoard::new(1 << i);
+        let attacks = match color {
+            Color::White => pawn.north_east().bits() | pawn.north_west().bits(),
+            Color::Black => pawn.south_east().bits() | pawn.south_west().bits(),
+        };
+        boards[i] = BitBoard::new(attacks);
+        i += 1;
+    }
+
+    boards
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::bitboard::{FILEA, FILEH};
+
+    /// Reference attacks built by stepping `offsets` from `square` one at a time
+    fn reference_attacks(
+        square: Square,
+        offsets: &[(i8, i8)],
+        slide: bool,
+        occ: BitBoard,
+    ) -> BitBoard {
+        let mut attacks = BitBoard::new(0);
+        for (dr, df) in offsets {
+            let mut rank = square.rank() as i8 + dr;
+            let mut file = square.file() as i8 + df;
+            while (0..8).contains(&rank) && (0..8).contains(&file) {
+                let target = Square::from((rank * 8 + file) as u8);
+                attacks.set_square(target);
+                if !slide || occ.contains(target) {
+                    break;
+                }
+                rank += dr;
+                file += df;
+            }
+        }
+        attacks
+    }
+
+    const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+        (1, 2),
+        (2, 1),
+        (2, -1),
+        (1, -2),
+        (-1, -2),
+        (-2, -1),
+        (-2, 1),
+        (-1, 2),
+    ];
+    const KING_OFFSETS: [(i8, i8); 8] = [
+        (1, 0),
+        (1, 1),
+        (0, 1),
+        (-1, 1),
+        (-1, 0),
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+    ];
+    const ROOK_OFFSETS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    const BISHOP_OFFSETS: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+    /// A handful of deterministic occupancies with pieces spread across the board
+    fn occupancies() -> impl Iterator<Item = BitBoard> {
+        let mut state = 0x9E3779B97F4A7C15u64;
+        (0..64).map(move |_| {
+            // xorshift, sparse enough to leave rays partially open
+            let mut next = || {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state
+            };
+            BitBoard::new(next() & next())
+        })
+    }
+
+    fn all_squares() -> impl Iterator<Item = Square> {
+        (0..64u8).map(Square::from)
+    }
+
+    #[test]
+    fn file_and_rank_masks() {
+        for square in all_squares() {
+            assert!(square.file().mask().contains(square));
+            assert!(square.rank().mask().contains(square));
+            assert_eq!(square.file().mask().popcount(), 8);
+            assert_eq!(square.rank().mask().popcount(), 8);
+        }
+        assert!(BitBoard::new(FILEA).contains(Square::A1));
+        assert!(BitBoard::new(FILEA).contains(Square::A8));
+        assert!(BitBoard::new(FILEH).contains(Square::H1));
+        assert!(BitBoard::new(FILEH).contains(Square::H8));
+    }
+
+    #[test]
+    fn shifts_never_wrap() {
+        for square in all_squares() {
+            let board = BitBoard::from_square(square);
+            for (shifted, (dr, df)) in [
+                (board.north(), (1, 0)),
+                (board.south(), (-1, 0)),
+                (board.east(), (0, 1)),
+                (board.west(), (0, -1)),
+                (board.north_east(), (1, 1)),
+                (board.north_west(), (1, -1)),
+                (board.south_east(), (-1, 1)),
+                (board.south_west(), (-1, -1)),
+            ] {
+                assert_eq!(
+                    shifted,
+                    reference_attacks(square, &[(dr, df)], false, board)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn knight_attacks() {
+        for square in all_squares() {
+            let expected = reference_attacks(square, &KNIGHT_OFFSETS, false, BitBoard::new(0));
+            assert_eq!(KNIGHT_ATTACKS[square as usize], expected, "{square}");
+        }
+    }
+
+    #[test]
+    fn king_attacks() {
+        for square in all_squares() {
+            let expected = reference_attacks(square, &KING_OFFSETS, false, BitBoard::new(0));
+            assert_eq!(KING_ATTACKS[square as usize], expected, "{square}");
+        }
+    }
+
+    #[test]
+    fn pawn_attacks() {
+        for square in all_squares() {
+            let white = reference_attacks(square, &[(1, 1), (1, -1)], false, BitBoard::new(0));
+            let black = reference_attacks(square, &[(-1, 1), (-1, -1)], false, BitBoard::new(0));
+            assert_eq!(get_pawn_attacks(square, Color::White), white, "{square}");
+            assert_eq!(get_pawn_attacks(square, Color::Black), black, "{square}");
+        }
+    }
+
+    #[test]
+    fn rook_attacks() {
+        for square in all_squares() {
+            for occ in occupancies() {
+                let expected = reference_attacks(square, &ROOK_OFFSETS, true, occ);
+                assert_eq!(get_rook_attacks(square, occ), expected, "{square}");
+            }
+        }
+    }
+
+    #[test]
+    fn bishop_attacks() {
+        for square in all_squares() {
+            for occ in occupancies() {
+                let expected = reference_attacks(square, &BISHOP_OFFSETS, true, occ);
+                assert_eq!(get_bishop_attacks(square, occ), expected, "{square}");
+            }
+        }
+    }
+}
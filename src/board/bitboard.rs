@@ -10,23 +10,105 @@ use std::{
     str::FromStr,
 };
 
-pub const RANK8: u64 = 0xFF << 7 * 8;
-pub const RANK7: u64 = 0xFF << 6 * 8;
-pub const RANK6: u64 = 0xFF << 5 * 8;
-pub const RANK5: u64 = 0xFF << 4 * 8;
-pub const RANK4: u64 = 0xFF << 3 * 8;
-pub const RANK3: u64 = 0xFF << 2 * 8;
-pub const RANK2: u64 = 0xFF << 1 * 8;
 pub const RANK1: u64 = 0xFF;
+pub const RANK2: u64 = RANK1 << 8;
+pub const RANK3: u64 = RANK1 << (2 * 8);
+pub const RANK4: u64 = RANK1 << (3 * 8);
+pub const RANK5: u64 = RANK1 << (4 * 8);
+pub const RANK6: u64 = RANK1 << (5 * 8);
+pub const RANK7: u64 = RANK1 << (6 * 8);
+pub const RANK8: u64 = RANK1 << (7 * 8);
 
-pub const FILEA: u64 = 0x8080808080808080;
-pub const FILEH: u64 = FILEA >> 7;
+pub const FILEA: u64 = 0x0101010101010101;
+pub const FILEB: u64 = FILEA << 1;
+pub const FILEC: u64 = FILEA << 2;
+pub const FILED: u64 = FILEA << 3;
+pub const FILEE: u64 = FILEA << 4;
+pub const FILEF: u64 = FILEA << 5;
+pub const FILEG: u64 = FILEA << 6;
+pub const FILEH: u64 = FILEA << 7;
+
+/// A column of the board, `A` being the queen side edge
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum File {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+}
+
+impl File {
+    pub const ALL: [File; 8] = [
+        File::A,
+        File::B,
+        File::C,
+        File::D,
+        File::E,
+        File::F,
+        File::G,
+        File::H,
+    ];
+
+    /// Returns the file at `index`, counted from zero
+    ///
+    /// Panics if `index` is not below 8
+    pub const fn from_index(index: u8) -> Self {
+        Self::ALL[index as usize]
+    }
+
+    /// Returns a mask of every square on the file
+    pub const fn mask(self) -> BitBoard {
+        BitBoard::new(FILEA << self as u8)
+    }
+}
+
+/// A row of the board, `First` being white's back rank
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Fifth,
+    Sixth,
+    Seventh,
+    Eighth,
+}
+
+impl Rank {
+    pub const ALL: [Rank; 8] = [
+        Rank::First,
+        Rank::Second,
+        Rank::Third,
+        Rank::Fourth,
+        Rank::Fifth,
+        Rank::Sixth,
+        Rank::Seventh,
+        Rank::Eighth,
+    ];
+
+    /// Returns the rank at `index`, counted from zero
+    ///
+    /// Panics if `index` is not below 8
+    pub const fn from_index(index: u8) -> Self {
+        Self::ALL[index as usize]
+    }
+
+    /// Returns a mask of every square on the rank
+    pub const fn mask(self) -> BitBoard {
+        BitBoard::new(RANK1 << (self as u8 * 8))
+    }
+}
 
 /// Maps chess board names to indexes in a BitBoard
 ///
 /// Bitboard are indexed using Little-Endian Rank-File Mapping
 #[rustfmt::skip]
-#[derive(Clone, Copy, Debug, FromPrimitive)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, FromPrimitive)]
 pub enum Square {
     A1 = 0, B1, C1, D1, E1, F1, G1, H1,
     A2, B2, C2, D2, E2, F2, G2, H2,
@@ -38,6 +120,23 @@ pub enum Square {
     A8, B8, C8, D8, E8, F8, G8, H8,
 }
 
+impl Square {
+    /// Returns the square on `rank` and `file`
+    pub fn new(rank: Rank, file: File) -> Self {
+        Self::from(rank as u8 * 8 + file as u8)
+    }
+
+    /// Returns the rank of the square
+    pub const fn rank(self) -> Rank {
+        Rank::from_index(self as u8 / 8)
+    }
+
+    /// Returns the file of the square
+    pub const fn file(self) -> File {
+        File::from_index(self as u8 % 8)
+    }
+}
+
 impl Display for Square {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let index = *self as u8;
@@ -76,10 +175,54 @@ impl From<u8> for Square {
     }
 }
 
+/// The eight compass directions a piece can move in, north being towards rank 8
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::SouthWest,
+        Direction::NorthWest,
+        Direction::SouthEast,
+    ];
+
+    /// Returns the (rank, file) step of one move in this direction
+    const fn offset(self) -> (i8, i8) {
+        match self {
+            Direction::North => (1, 0),
+            Direction::South => (-1, 0),
+            Direction::East => (0, 1),
+            Direction::West => (0, -1),
+            Direction::NorthEast => (1, 1),
+            Direction::NorthWest => (1, -1),
+            Direction::SouthEast => (-1, 1),
+            Direction::SouthWest => (-1, -1),
+        }
+    }
+}
+
 #[derive(
     Debug,
+    Default,
     Clone,
     Copy,
+    PartialEq,
+    Eq,
+    Hash,
     From,
     Not,
     BitOr,
@@ -124,6 +267,238 @@ impl BitBoard {
     pub fn trailing_zeros(&self) -> u8 {
         self.0.trailing_zeros() as u8
     }
+
+    /// Returns a board with only `square` set
+    pub const fn from_square(square: Square) -> Self {
+        Self(1 << square as u8)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns a mask of every square on `file`
+    pub const fn file(file: File) -> Self {
+        file.mask()
+    }
+
+    /// Returns a mask of every square on `rank`
+    pub const fn rank(rank: Rank) -> Self {
+        rank.mask()
+    }
+
+    /// Returns a mask of the a1-h8 direction diagonal passing through `square`
+    pub const fn diagonal(square: Square) -> Self {
+        const MAIN_DIAGONAL: u64 = 0x8040201008040201;
+        let offset = square.file() as i32 - square.rank() as i32;
+        if offset >= 0 {
+            Self(MAIN_DIAGONAL >> (offset * 8))
+        } else {
+            Self(MAIN_DIAGONAL << (-offset * 8))
+        }
+    }
+
+    /// Returns a mask of the h1-a8 direction diagonal passing through `square`
+    pub const fn anti_diagonal(square: Square) -> Self {
+        const MAIN_ANTI_DIAGONAL: u64 = 0x0102040810204080;
+        let offset = square.file() as i32 + square.rank() as i32 - 7;
+        if offset >= 0 {
+            Self(MAIN_ANTI_DIAGONAL << (offset * 8))
+        } else {
+            Self(MAIN_ANTI_DIAGONAL >> (-offset * 8))
+        }
+    }
+
+    /// Returns the number of set squares
+    pub const fn popcount(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(&self, square: Square) -> bool {
+        self.0 & (1 << square as u8) != 0
+    }
+
+    /// Returns the least significant set square
+    pub fn lsb(&self) -> Option<Square> {
+        (!self.is_empty()).then(|| Square::from(self.0.trailing_zeros() as u8))
+    }
+
+    /// Returns the most significant set square
+    pub fn msb(&self) -> Option<Square> {
+        (!self.is_empty()).then(|| Square::from(63 - self.0.leading_zeros() as u8))
+    }
+
+    /// Removes and returns the least significant set square
+    pub fn pop_lsb(&mut self) -> Option<Square> {
+        let square = self.lsb()?;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+
+    /// Moves every square one step in `dir`, dropping squares that would leave the board
+    pub const fn shift(self, dir: Direction) -> Self {
+        match dir {
+            Direction::North => self.north(),
+            Direction::South => self.south(),
+            Direction::East => self.east(),
+            Direction::West => self.west(),
+            Direction::NorthEast => self.north_east(),
+            Direction::NorthWest => self.north_west(),
+            Direction::SouthEast => self.south_east(),
+            Direction::SouthWest => self.south_west(),
+        }
+    }
+
+    pub const fn north(self) -> Self {
+        Self(self.0 << 8)
+    }
+
+    pub const fn south(self) -> Self {
+        Self(self.0 >> 8)
+    }
+
+    /// Squares on the h-file are dropped instead of wrapping onto the a-file
+    pub const fn east(self) -> Self {
+        Self((self.0 & !FILEH) << 1)
+    }
+
+    /// Squares on the a-file are dropped instead of wrapping onto the h-file
+    pub const fn west(self) -> Self {
+        Self((self.0 & !FILEA) >> 1)
+    }
+
+    pub const fn north_east(self) -> Self {
+        self.east().north()
+    }
+
+    pub const fn north_west(self) -> Self {
+        self.west().north()
+    }
+
+    pub const fn south_east(self) -> Self {
+        self.east().south()
+    }
+
+    pub const fn south_west(self) -> Self {
+        self.west().south()
+    }
+}
+
+/// Iterates over the set squares from least to most significant
+impl Iterator for BitBoard {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pop_lsb()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.popcount() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for BitBoard {}
+
+impl From<Square> for BitBoard {
+    fn from(square: Square) -> Self {
+        Self::from_square(square)
+    }
+}
+
+static BETWEEN: [[BitBoard; 64]; 64] = gen_between();
+static LINE: [[BitBoard; 64]; 64] = gen_line();
+
+/// Returns the squares strictly between `a` and `b`
+///
+/// Empty if the two squares don't share a rank, file or diagonal.
+pub fn between(a: Square, b: Square) -> BitBoard {
+    BETWEEN[a as usize][b as usize]
+}
+
+/// Returns the full line across the board through `a` and `b`, including both
+///
+/// Empty if the two squares don't share a rank, file or diagonal.
+pub fn line(a: Square, b: Square) -> BitBoard {
+    LINE[a as usize][b as usize]
+}
+
+/// Walks from `square` in direction `dir`, returning the index of every square passed
+const fn ray(square: usize, dir: Direction) -> ([usize; 7], usize) {
+    let (dr, df) = dir.offset();
+    let mut squares = [0; 7];
+    let mut len = 0;
+    let mut rank = (square / 8) as i8 + dr;
+    let mut file = (square % 8) as i8 + df;
+    while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+        squares[len] = (rank * 8 + file) as usize;
+        len += 1;
+        rank += dr;
+        file += df;
+    }
+    (squares, len)
+}
+
+const fn gen_between() -> [[BitBoard; 64]; 64] {
+    let mut table = [[BitBoard::new(0); 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut d = 0;
+        while d < Direction::ALL.len() {
+            let (squares, len) = ray(a, Direction::ALL[d]);
+            let mut passed = 0;
+            let mut i = 0;
+            while i < len {
+                table[a][squares[i]] = BitBoard::new(passed);
+                passed |= 1 << squares[i];
+                i += 1;
+            }
+            d += 1;
+        }
+        a += 1;
+    }
+    table
+}
+
+const fn gen_line() -> [[BitBoard; 64]; 64] {
+    let mut table = [[BitBoard::new(0); 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        // directions come in opposite pairs in `Direction::ALL`
+        let mut d = 0;
+        while d < Direction::ALL.len() {
+            let (forward, forward_len) = ray(a, Direction::ALL[d]);
+            let (backward, backward_len) = ray(a, Direction::ALL[d + 1]);
+            let mut full = 1 << a;
+            let mut i = 0;
+            while i < forward_len {
+                full |= 1 << forward[i];
+                i += 1;
+            }
+            i = 0;
+            while i < backward_len {
+                full |= 1 << backward[i];
+                i += 1;
+            }
+            i = 0;
+            while i < forward_len {
+                table[a][forward[i]] = BitBoard::new(full);
+                i += 1;
+            }
+            i = 0;
+            while i < backward_len {
+                table[a][backward[i]] = BitBoard::new(full);
+                i += 1;
+            }
+            d += 2;
+        }
+        a += 1;
+    }
+    table
 }
 
 impl Display for BitBoard {
@@ -176,3 +551,62 @@ impl PartialEq<u64> for BitBoard {
         self.0 == *other
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squares(squares: &[Square]) -> BitBoard {
+        squares
+            .iter()
+            .fold(BitBoard::new(0), |board, &square| board | square.into())
+    }
+
+    #[test]
+    fn lines_and_diagonals() {
+        use Square::*;
+        assert_eq!(BitBoard::diagonal(C1), squares(&[C1, D2, E3, F4, G5, H6]));
+        assert_eq!(BitBoard::diagonal(A8), squares(&[A8]));
+        assert_eq!(
+            BitBoard::anti_diagonal(E2),
+            squares(&[F1, E2, D3, C4, B5, A6])
+        );
+        assert_eq!(BitBoard::anti_diagonal(H8), squares(&[H8]));
+
+        assert_eq!(between(A1, A4), squares(&[A2, A3]));
+        assert_eq!(between(H8, C3), squares(&[G7, F6, E5, D4]));
+        assert_eq!(between(B2, C3), BitBoard::new(0));
+        assert_eq!(between(A1, B3), BitBoard::new(0));
+        assert_eq!(line(C3, E5), BitBoard::diagonal(A1));
+        assert_eq!(line(D1, D7), File::D.mask());
+        assert_eq!(line(G2, F3), BitBoard::anti_diagonal(A8));
+        assert_eq!(line(A1, B3), BitBoard::new(0));
+    }
+
+    #[test]
+    fn scanning() {
+        use Square::*;
+        let mut board = squares(&[B2, E5, H8]);
+        assert_eq!(board.popcount(), 3);
+        assert_eq!(board.lsb(), Some(B2));
+        assert_eq!(board.msb(), Some(H8));
+        assert_eq!(board.pop_lsb(), Some(B2));
+        assert_eq!(board.collect::<Vec<_>>(), vec![E5, H8]);
+        assert_eq!(BitBoard::new(0).lsb(), None);
+        assert_eq!(BitBoard::new(0).msb(), None);
+    }
+
+    #[test]
+    fn shifts_drop_squares_off_the_edge() {
+        use Square::*;
+        let corners = squares(&[A1, H1, A8, H8]);
+        assert_eq!(corners.shift(Direction::North), squares(&[A2, H2]));
+        assert_eq!(corners.shift(Direction::East), squares(&[B1, B8]));
+        assert_eq!(corners.shift(Direction::West), squares(&[G1, G8]));
+        assert_eq!(corners.shift(Direction::SouthWest), squares(&[G7]));
+        let center = BitBoard::from(E4);
+        for dir in Direction::ALL {
+            assert_eq!(center.shift(dir).popcount(), 1, "{dir:?}");
+        }
+    }
+}
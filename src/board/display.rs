@@ -0,0 +1,179 @@
+use std::fmt::Display;
+
+use super::{
+    bitboard::{BitBoard, Square},
+    Board,
+};
+use crate::moves::Move;
+
+/// A printable 8x8 diagram of a [Board]
+///
+/// Created with [Board::diagram]. By default pieces are drawn with ASCII letters
+/// from white's point of view. Highlighted squares are drawn as `[x]` and the
+/// squares of the last move as `(x)`.
+#[derive(Clone, Copy, Debug)]
+pub struct Diagram<'a> {
+    board: &'a Board,
+    unicode: bool,
+    flipped: bool,
+    highlight: BitBoard,
+    last_move: Option<Move>,
+}
+
+impl<'a> Diagram<'a> {
+    fn new(board: &'a Board) -> Self {
+        Self {
+            board,
+            unicode: false,
+            flipped: false,
+            highlight: BitBoard::new(0),
+            last_move: None,
+        }
+    }
+
+    /// Draw pieces with Unicode chess glyphs instead of letters
+    pub fn unicode(mut self, unicode: bool) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    /// Draw the board from black's point of view
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    /// Mark every square set in `squares`
+    pub fn highlight(mut self, squares: BitBoard) -> Self {
+        self.highlight = squares;
+        self
+    }
+
+    /// Mark the origin and destination squares of `mv`
+    pub fn last_move(mut self, mv: Move) -> Self {
+        self.last_move = Some(mv);
+        self
+    }
+
+    fn square_char(&self, square: Square) -> char {
+        match self.board.piece_at(square) {
+            Some((piece, color)) if self.unicode => piece.to_unicode(color),
+            Some((piece, color)) => piece.to_char(color),
+            None if self.unicode => '·',
+            None => '.',
+        }
+    }
+
+    fn is_last_move(&self, square: Square) -> bool {
+        self.last_move
+            .is_some_and(|mv| mv.from == square || mv.to == square)
+    }
+}
+
+impl Display for Diagram<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let order = |i: u8| if self.flipped { i } else { 7 - i };
+        for row in 0..8 {
+            let rank = order(row);
+            write!(f, "{} ", rank + 1)?;
+            for col in 0..8 {
+                let file = 7 - order(col);
+                let square = Square::from(rank * 8 + file);
+                let ch = self.square_char(square);
+                if self.highlight.contains(square) {
+                    write!(f, "[{ch}]")?;
+                } else if self.is_last_move(square) {
+                    write!(f, "({ch})")?;
+                } else {
+                    write!(f, " {ch} ")?;
+                }
+            }
+            writeln!(f)?;
+        }
+        write!(f, "  ")?;
+        for col in 0..8 {
+            write!(f, " {} ", (b'a' + 7 - order(col)) as char)?;
+        }
+        writeln!(f)
+    }
+}
+
+impl Board {
+    /// Returns a [Diagram] of the board, which can be configured before printing
+    pub fn diagram(&self) -> Diagram<'_> {
+        Diagram::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_diagram() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.diagram().to_string(),
+            "8  .  .  .  .  k  .  .  . \n\
+             7  .  .  .  .  .  .  .  . \n\
+             6  .  .  .  .  .  .  .  . \n\
+             5  .  .  .  .  .  .  .  . \n\
+             4  .  .  .  .  .  .  .  . \n\
+             3  .  .  .  .  .  .  .  . \n\
+             2  .  .  .  .  P  .  .  . \n\
+             1  .  .  .  .  K  .  .  . \n\
+             \x20  a  b  c  d  e  f  g  h \n"
+        );
+    }
+
+    #[test]
+    fn flipped_unicode() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.diagram().unicode(true).flipped(true).to_string(),
+            "1  ·  ·  ·  ♔  ·  ·  ·  · \n\
+             2  ·  ·  ·  ♙  ·  ·  ·  · \n\
+             3  ·  ·  ·  ·  ·  ·  ·  · \n\
+             4  ·  ·  ·  ·  ·  ·  ·  · \n\
+             5  ·  ·  ·  ·  ·  ·  ·  · \n\
+             6  ·  ·  ·  ·  ·  ·  ·  · \n\
+             7  ·  ·  ·  ·  ·  ·  ·  · \n\
+             8  ·  ·  ·  ♚  ·  ·  ·  · \n\
+             \x20  h  g  f  e  d  c  b  a \n"
+        );
+    }
+
+    #[test]
+    fn marked_squares() {
+        let board = Board::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+        let diagram = board
+            .diagram()
+            .highlight(BitBoard::from(Square::E1) | BitBoard::from(Square::D2))
+            .last_move(Move::new(Square::E4, Square::E2, None));
+        // highlighting replaces earlier highlights and wins over the last move
+        assert_eq!(
+            diagram.highlight(BitBoard::from(Square::E4)).to_string(),
+            "8  .  .  .  .  k  .  .  . \n\
+             7  .  .  .  .  .  .  .  . \n\
+             6  .  .  .  .  .  .  .  . \n\
+             5  .  .  .  .  .  .  .  . \n\
+             4  .  .  .  . [P] .  .  . \n\
+             3  .  .  .  .  .  .  .  . \n\
+             2  .  .  .  . (.) .  .  . \n\
+             1  .  .  .  .  K  .  .  . \n\
+             \x20  a  b  c  d  e  f  g  h \n"
+        );
+        assert_eq!(
+            diagram.to_string(),
+            "8  .  .  .  .  k  .  .  . \n\
+             7  .  .  .  .  .  .  .  . \n\
+             6  .  .  .  .  .  .  .  . \n\
+             5  .  .  .  .  .  .  .  . \n\
+             4  .  .  .  . (P) .  .  . \n\
+             3  .  .  .  .  .  .  .  . \n\
+             2  .  .  . [.](.) .  .  . \n\
+             1  .  .  .  . [K] .  .  . \n\
+             \x20  a  b  c  d  e  f  g  h \n"
+        );
+    }
+}
@@ -1,15 +1,21 @@
-use super::{Board, CastleRights, Color, Piece};
+use super::{
+    bitboard::{Rank, Square},
+    Board, CastleRights, Color, Piece,
+};
 
 impl Board {
+    /// Parses a position in Forsyth-Edwards Notation, returns `None` if it is malformed, either
+    /// side doesn't have exactly one king or the side that just moved is in check
+    ///
+    /// The half and full move counters may be left out, they then default to 0 and 1.
     pub fn from_fen(fen: &str) -> Option<Self> {
         let mut board = Board::new();
         let mut add_piece = |piece: Piece, color: Color, rank, file| {
-            let index = piece as usize + color as usize;
-            board.pieces[index].set_rank_file(rank, file);
+            board.put_piece(piece, color, Square::from(rank * 8 + file));
         };
         // Split apart the fen string
-        let parts: Vec<&str> = fen.split(' ').collect();
-        if parts.len() != 6 {
+        let parts: Vec<&str> = fen.split_whitespace().collect();
+        if parts.len() != 4 && parts.len() != 6 {
             return None;
         }
         // Parse piece positions
@@ -46,11 +52,16 @@ impl Board {
             }
         }
 
-        board.pieces[Color::White as usize + 6] = board.white_pieces();
-        board.pieces[Color::Black as usize + 6] = board.black_pieces();
+        // everything else assumes a king of each color
+        if Color::ALL
+            .iter()
+            .any(|color| board.piece(Piece::King, *color).popcount() != 1)
+        {
+            return None;
+        }
 
         // Parse side to move
-        board.side_to_move = if parts[1].chars().next().unwrap() == 'w' {
+        board.side_to_move = if parts[1].starts_with('w') {
             Color::White
         } else {
             Color::Black
@@ -80,12 +91,76 @@ impl Board {
         board.valid_en_passant = if parts[3] == "-" {
             None
         } else {
-            Some(parts[3].parse().ok()?)
+            let square: Square = parts[3].parse().ok()?;
+            // the square a double push skipped, behind the pawn of the side that just moved
+            let skipped = match board.side_to_move {
+                Color::White => Rank::Sixth,
+                Color::Black => Rank::Third,
+            };
+            if square.rank() != skipped {
+                return None;
+            }
+            board.en_passant_target(square, board.side_to_move.opposite())
         };
         // Parse half and full time
-        board.half_moves = parts[4].parse().ok()?;
-        board.full_moves = parts[5].parse().ok()?;
+        if parts.len() == 6 {
+            board.half_moves = parts[4].parse().ok()?;
+            board.full_moves = parts[5].parse().ok()?;
+        } else {
+            board.full_moves = 1;
+        }
+        board.hash = board.compute_hash();
 
+        // the side to move could take the king
+        let them = board.side_to_move.opposite();
+        if board.is_attacked(board.king_square(them), board.side_to_move) {
+            return None;
+        }
+        if cfg!(debug_assertions) {
+            board.assert_consistent();
+        }
         Some(board)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_unusable_en_passant_squares() {
+        let board =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        let without =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(board.valid_en_passant, None);
+        assert_eq!(board.hash(), without.hash());
+
+        // the pawn on d4 can take en passant
+        let board = Board::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+            .unwrap();
+        assert_eq!(board.valid_en_passant, Some(Square::E3));
+    }
+
+    #[test]
+    fn validation() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+        assert_eq!(board.side_to_move(), Color::Black);
+        assert_eq!((board.half_moves(), board.full_moves()), (0, 1));
+
+        for fen in [
+            "8/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3KK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0",
+            "4k3/8/8/8/8/8/8/4K3 w -",
+            // the side not to move is in check
+            "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1",
+            // en passant squares off the rank a double push skips
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d5 0 1",
+            "4k3/8/8/8/3pP3/8/8/4K3 w - e3 0 1",
+        ] {
+            assert!(Board::from_fen(fen).is_none(), "{fen}");
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use super::bitboard::BitBoard;
+use std::sync::LazyLock;
+
+use super::bitboard::{BitBoard, Square};
 
 const ROOK_MAGIC_NUMBERS: [u64; 64] = [
     0xa8002c000108020,
@@ -134,22 +136,156 @@ const BISHOP_MAGIC_NUMBERS: [u64; 64] = [
     0x40102000a0a60140,
 ];
 
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
 const ROOK_MAGICS: [MagicEntry; 64] = init_rook_magics();
 const BISHOP_MAGICS: [MagicEntry; 64] = init_bishop_magics();
 
+const ROOK_TABLE_SIZE: usize = table_size(&ROOK_MAGICS);
+const BISHOP_TABLE_SIZE: usize = table_size(&BISHOP_MAGICS);
+
+// The attack tables are filled on first use, building them in const eval adds seconds to every compile
+static ROOK_ATTACKS: LazyLock<Box<[BitBoard]>> =
+    LazyLock::new(|| gen_attack_table(&ROOK_MAGICS, &ROOK_DIRECTIONS, ROOK_TABLE_SIZE));
+static BISHOP_ATTACKS: LazyLock<Box<[BitBoard]>> =
+    LazyLock::new(|| gen_attack_table(&BISHOP_MAGICS, &BISHOP_DIRECTIONS, BISHOP_TABLE_SIZE));
+
 #[derive(Clone, Copy, Debug)]
 pub struct MagicEntry {
     mask: BitBoard,
     magic: u64,
     shift: u8,
+    /// Index of the square's first entry in the shared attack table
+    offset: usize,
 }
 
-const fn init_rook_magics() -> [MagicEntry; 64] {
-    todo!()
+impl MagicEntry {
+    const fn index(&self, occ: u64) -> usize {
+        let relevant = occ & self.mask.bits();
+        self.offset + (relevant.wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+/// Returns the squares a rook on `square` attacks, stopping at the first blocker in each direction
+pub fn rook_attacks(square: Square, occ: BitBoard) -> BitBoard {
+    ROOK_ATTACKS[ROOK_MAGICS[square as usize].index(occ.bits())]
+}
+
+/// Returns the squares a bishop on `square` attacks, stopping at the first blocker in each direction
+pub fn bishop_attacks(square: Square, occ: BitBoard) -> BitBoard {
+    BISHOP_ATTACKS[BISHOP_MAGICS[square as usize].index(occ.bits())]
+}
+
+/// Walks every direction from `square` until the edge of the board or a blocker in `occ`
+const fn sliding_attacks(square: usize, occ: u64, directions: &[(i8, i8); 4]) -> u64 {
+    let mut attacks = 0;
+    let mut d = 0;
+    while d < directions.len() {
+        let (dr, df) = directions[d];
+        let mut rank = (square / 8) as i8 + dr;
+        let mut file = (square % 8) as i8 + df;
+        while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+            let bit = 1 << (rank * 8 + file);
+            attacks |= bit;
+            if occ & bit != 0 {
+                break;
+            }
+            rank += dr;
+            file += df;
+        }
+        d += 1;
+    }
+    attacks
+}
+
+/// Returns the squares whose occupancy changes the attacks from `square`
+///
+/// The last square of each ray is left out since a blocker there doesn't hide anything.
+const fn relevant_mask(square: usize, directions: &[(i8, i8); 4]) -> u64 {
+    let mut mask = 0;
+    let mut d = 0;
+    while d < directions.len() {
+        let (dr, df) = directions[d];
+        let mut rank = (square / 8) as i8 + dr;
+        let mut file = (square % 8) as i8 + df;
+        while rank + dr >= 0 && rank + dr < 8 && file + df >= 0 && file + df < 8 {
+            mask |= 1 << (rank * 8 + file);
+            rank += dr;
+            file += df;
+        }
+        d += 1;
+    }
+    mask
 }
 
-// const fn gen_bishop_
+const fn init_magics(numbers: &[u64; 64], directions: &[(i8, i8); 4]) -> [MagicEntry; 64] {
+    let mut entries = [MagicEntry {
+        mask: BitBoard::new(0),
+        magic: 0,
+        shift: 0,
+        offset: 0,
+    }; 64];
+
+    let mut offset = 0;
+    let mut square = 0;
+    while square < 64 {
+        let mask = relevant_mask(square, directions);
+        let bits = mask.count_ones();
+        entries[square] = MagicEntry {
+            mask: BitBoard::new(mask),
+            magic: numbers[square],
+            shift: (64 - bits) as u8,
+            offset,
+        };
+        offset += 1 << bits;
+        square += 1;
+    }
+
+    entries
+}
+
+const fn init_rook_magics() -> [MagicEntry; 64] {
+    init_magics(&ROOK_MAGIC_NUMBERS, &ROOK_DIRECTIONS)
+}
 
 const fn init_bishop_magics() -> [MagicEntry; 64] {
-    todo!()
+    init_magics(&BISHOP_MAGIC_NUMBERS, &BISHOP_DIRECTIONS)
+}
+
+const fn table_size(entries: &[MagicEntry; 64]) -> usize {
+    let last = &entries[63];
+    last.offset + (1 << (64 - last.shift))
+}
+
+/// Fills the attack table for every subset of every square's relevant mask
+///
+/// Panics if one of the magic numbers maps two subsets with different attacks to the same slot.
+fn gen_attack_table(
+    entries: &[MagicEntry; 64],
+    directions: &[(i8, i8); 4],
+    size: usize,
+) -> Box<[BitBoard]> {
+    let mut table = vec![BitBoard::new(0); size].into_boxed_slice();
+
+    for (square, entry) in entries.iter().enumerate() {
+        let mask = entry.mask.bits();
+        // enumerate all subsets of the mask with the Carry-Rippler trick
+        let mut subset: u64 = 0;
+        loop {
+            let index = entry.index(subset);
+            let attacks = BitBoard::new(sliding_attacks(square, subset, directions));
+            assert!(
+                table[index].is_empty() || table[index] == attacks,
+                "magic number collision on square {square}"
+            );
+            table[index] = attacks;
+            subset = subset.wrapping_sub(mask) & mask;
+            if subset == 0 {
+                break;
+            }
+        }
+    }
+
+    table
 }
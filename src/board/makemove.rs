@@ -0,0 +1,216 @@
+use super::{attacks::get_pawn_attacks, bitboard::Square, Board, CastleRights, Color, Piece};
+use crate::moves::Move;
+
+/// The parts of a [Board] that can't be recovered from a [Move] alone,
+/// returned by [Board::make_move] so the move can be taken back
+#[derive(Clone, Copy, Debug)]
+pub struct Undo {
+    captured: Option<Piece>,
+    white_castle_rights: CastleRights,
+    black_castle_rights: CastleRights,
+    valid_en_passant: Option<Square>,
+    half_moves: u32,
+    hash: u64,
+}
+
+impl Board {
+    /// Plays the pseudo-legal `mv` for [side_to_move][Self::side_to_move]
+    ///
+    /// Castling is encoded as the king moving two squares and en passant as a pawn
+    /// moving onto the en passant square.
+    pub fn make_move(&mut self, mv: Move) -> Undo {
+        let mut undo = Undo {
+            captured: None,
+            white_castle_rights: self.white_castle_rights,
+            black_castle_rights: self.black_castle_rights,
+            valid_en_passant: self.valid_en_passant,
+            half_moves: self.half_moves,
+            hash: self.hash,
+        };
+        let us = self.side_to_move;
+        // the state part of the key is swapped out as a whole once the move is made
+        self.hash ^= self.state_key();
+
+        let (piece, _) = self
+            .remove_piece(mv.from)
+            .expect("move origin should hold a piece");
+        let capture_square = if self.is_en_passant(piece, mv.to) {
+            en_passant_victim(mv.to, us)
+        } else {
+            mv.to
+        };
+        undo.captured = self.remove_piece(capture_square).map(|(piece, _)| piece);
+
+        if let (Piece::King, Some((rook_from, rook_to))) = (piece, castling_rook(mv)) {
+            self.remove_piece(rook_from);
+            self.put_piece(Piece::Rook, us, rook_to);
+        }
+        let placed = mv.promotion.map_or(piece, |promotion| promotion.piece());
+        self.put_piece(placed, us, mv.to);
+
+        self.valid_en_passant = if piece == Piece::Pawn && (mv.from as i8 - mv.to as i8).abs() == 16
+        {
+            self.en_passant_target(Square::from((mv.from as u8 + mv.to as u8) / 2), us)
+        } else {
+            None
+        };
+        self.update_castle_rights(mv.from);
+        self.update_castle_rights(mv.to);
+
+        if piece == Piece::Pawn || undo.captured.is_some() {
+            self.half_moves = 0;
+        } else {
+            self.half_moves += 1;
+        }
+        if us == Color::Black {
+            self.full_moves += 1;
+        }
+        self.side_to_move = us.opposite();
+        self.hash ^= self.state_key();
+        self.refresh_nnue();
+
+        undo
+    }
+
+    /// Takes back `mv`, which must be the last move played with [Board::make_move]
+    pub fn unmake_move(&mut self, mv: Move, undo: Undo) {
+        let us = self.side_to_move.opposite();
+        self.side_to_move = us;
+        if us == Color::Black {
+            self.full_moves -= 1;
+        }
+        self.white_castle_rights = undo.white_castle_rights;
+        self.black_castle_rights = undo.black_castle_rights;
+        self.valid_en_passant = undo.valid_en_passant;
+        self.half_moves = undo.half_moves;
+
+        let (placed, _) = self
+            .remove_piece(mv.to)
+            .expect("move destination should hold the moved piece");
+        let piece = if mv.promotion.is_some() {
+            Piece::Pawn
+        } else {
+            placed
+        };
+        self.put_piece(piece, us, mv.from);
+
+        if let (Piece::King, Some((rook_from, rook_to))) = (piece, castling_rook(mv)) {
+            self.remove_piece(rook_to);
+            self.put_piece(Piece::Rook, us, rook_from);
+        }
+        if let Some(captured) = undo.captured {
+            let square = if self.is_en_passant(piece, mv.to) {
+                en_passant_victim(mv.to, us)
+            } else {
+                mv.to
+            };
+            self.put_piece(captured, us.opposite(), square);
+        }
+        self.hash = undo.hash;
+        self.refresh_nnue();
+    }
+
+    /// Passes the turn to the opponent without moving, for null-move pruning
+    ///
+    /// The fifty-move counter is reset so that repetitions aren't looked for across the
+    /// null move, where they would be made up.
+    pub fn make_null_move(&mut self) -> Undo {
+        let undo = Undo {
+            captured: None,
+            white_castle_rights: self.white_castle_rights,
+            black_castle_rights: self.black_castle_rights,
+            valid_en_passant: self.valid_en_passant,
+            half_moves: self.half_moves,
+            hash: self.hash,
+        };
+        self.hash ^= self.state_key();
+        self.valid_en_passant = None;
+        self.half_moves = 0;
+        self.side_to_move = self.side_to_move.opposite();
+        self.hash ^= self.state_key();
+        undo
+    }
+
+    /// Takes back the null move played with [Board::make_null_move]
+    pub fn unmake_null_move(&mut self, undo: Undo) {
+        self.side_to_move = self.side_to_move.opposite();
+        self.valid_en_passant = undo.valid_en_passant;
+        self.half_moves = undo.half_moves;
+        self.hash = undo.hash;
+    }
+
+    pub(super) fn is_en_passant(&self, piece: Piece, to: Square) -> bool {
+        piece == Piece::Pawn && self.valid_en_passant == Some(to)
+    }
+
+    /// Returns `square`, skipped by a double push of `pusher`, if a pawn of the other side can
+    /// capture onto it
+    ///
+    /// Unusable en passant squares are dropped so that positions differing only in one
+    /// hash the same.
+    pub(super) fn en_passant_target(&self, square: Square, pusher: Color) -> Option<Square> {
+        let capturers =
+            get_pawn_attacks(square, pusher) & self.piece(Piece::Pawn, pusher.opposite());
+        (!capturers.is_empty()).then_some(square)
+    }
+
+    /// Removes the castling rights that depend on a king or rook standing on `square`
+    fn update_castle_rights(&mut self, square: Square) {
+        let none = CastleRights {
+            king: false,
+            queen: false,
+        };
+        match square {
+            Square::E1 => self.white_castle_rights = none,
+            Square::A1 => self.white_castle_rights.queen = false,
+            Square::H1 => self.white_castle_rights.king = false,
+            Square::E8 => self.black_castle_rights = none,
+            Square::A8 => self.black_castle_rights.queen = false,
+            Square::H8 => self.black_castle_rights.king = false,
+            _ => {}
+        }
+    }
+}
+
+/// Returns the square of the pawn captured by `color` moving en passant onto `to`
+pub(super) fn en_passant_victim(to: Square, color: Color) -> Square {
+    match color {
+        Color::White => Square::from(to as u8 - 8),
+        Color::Black => Square::from(to as u8 + 8),
+    }
+}
+
+/// Returns the origin and destination of the rook if `mv` is a castling king move
+fn castling_rook(mv: Move) -> Option<(Square, Square)> {
+    match (mv.from, mv.to) {
+        (Square::E1, Square::G1) => Some((Square::H1, Square::F1)),
+        (Square::E1, Square::C1) => Some((Square::A1, Square::D1)),
+        (Square::E8, Square::G8) => Some((Square::H8, Square::F8)),
+        (Square::E8, Square::C8) => Some((Square::A8, Square::D8)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn null_move_round_trip() {
+        let mut board =
+            Board::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3")
+                .unwrap();
+        let before = board.clone();
+        let undo = board.make_null_move();
+        assert_eq!(board.side_to_move(), Color::White);
+        assert_eq!(board.valid_en_passant, None);
+        assert_eq!(board.hash(), board.compute_hash());
+        assert_ne!(board.hash(), before.hash());
+
+        board.unmake_null_move(undo);
+        assert_eq!(board.side_to_move(), Color::Black);
+        assert_eq!(board.valid_en_passant, Some(Square::E3));
+        assert_eq!(board.hash(), before.hash());
+        board.assert_consistent();
+    }
+}
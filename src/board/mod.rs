@@ -1,12 +1,25 @@
+use std::sync::Arc;
+
 use bitboard::{BitBoard, Square};
+use psqt::{relative_square, MATERIAL, MAX_PHASE, PHASE_WEIGHTS, PIECE_SQUARES};
+use score::Score;
+
+use crate::eval::nnue::{accumulator::NnueState, Network};
 
-mod attacks;
+pub mod attacks;
 pub mod bitboard;
+pub mod display;
 pub mod fen;
 mod magic;
+pub mod makemove;
 pub mod movegen;
+pub mod psqt;
+pub mod score;
+pub mod see;
+pub mod svg;
+pub mod zobrist;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Piece {
     King,
     Queen,
@@ -16,10 +29,67 @@ pub enum Piece {
     Pawn,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Piece {
+    pub const ALL: [Piece; 6] = [
+        Piece::King,
+        Piece::Queen,
+        Piece::Rook,
+        Piece::Bishop,
+        Piece::Knight,
+        Piece::Pawn,
+    ];
+
+    /// Returns the FEN letter of the piece, uppercase for white and lowercase for black
+    pub fn to_char(self, color: Color) -> char {
+        let ch = match self {
+            Piece::King => 'K',
+            Piece::Queen => 'Q',
+            Piece::Rook => 'R',
+            Piece::Bishop => 'B',
+            Piece::Knight => 'N',
+            Piece::Pawn => 'P',
+        };
+        if let Color::White = color {
+            ch
+        } else {
+            ch.to_ascii_lowercase()
+        }
+    }
+
+    /// Returns the Unicode chess glyph of the piece
+    pub fn to_unicode(self, color: Color) -> char {
+        match (self, color) {
+            (Piece::King, Color::White) => '♔',
+            (Piece::Queen, Color::White) => '♕',
+            (Piece::Rook, Color::White) => '♖',
+            (Piece::Bishop, Color::White) => '♗',
+            (Piece::Knight, Color::White) => '♘',
+            (Piece::Pawn, Color::White) => '♙',
+            (Piece::King, Color::Black) => '♚',
+            (Piece::Queen, Color::Black) => '♛',
+            (Piece::Rook, Color::Black) => '♜',
+            (Piece::Bishop, Color::Black) => '♝',
+            (Piece::Knight, Color::Black) => '♞',
+            (Piece::Pawn, Color::Black) => '♟',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Color {
     White,
-    Black = 7,
+    Black,
+}
+
+impl Color {
+    pub const ALL: [Color; 2] = [Color::White, Color::Black];
+
+    pub const fn opposite(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
 }
 
 impl std::fmt::Display for Color {
@@ -38,7 +108,7 @@ pub struct CastleRights {
     pub queen: bool,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Board {
     side_to_move: Color,
     white_castle_rights: CastleRights,
@@ -46,11 +116,24 @@ pub struct Board {
     valid_en_passant: Option<Square>,
     half_moves: u32,
     full_moves: u32,
-    /// First 6 entrys are white's piece bitboards,
-    /// the 7th is a mask of all the white pieces on the board.
-    /// The next 6 entrys are black's piece bitboards,
-    /// and the last is a mask of all of black's pieces.
-    pieces: [BitBoard; 14],
+    /// Bitboard of every piece kind, indexed by `[color][piece]`
+    pieces: [[BitBoard; 6]; 2],
+    /// Mask of all pieces of each color, indexed by `[color]`
+    colors: [BitBoard; 2],
+    /// The piece on each square, indexed by [Square]
+    mailbox: [Option<(Piece, Color)>; 64],
+    /// Zobrist key of the position, kept up to date as pieces and state change
+    hash: u64,
+    /// Zobrist key of the pawns alone, keys the pawn structure cache
+    pawn_hash: u64,
+    /// Material of each color under [MATERIAL], indexed by `[color]`
+    material: [Score; 2],
+    /// Piece-square table totals of each color under [PIECE_SQUARES]
+    piece_squares: [Score; 2],
+    /// Sum of the phase weights of all pieces, not clamped to [MAX_PHASE]
+    phase: i32,
+    /// Network and accumulator for the NNUE evaluation, if a network is set
+    nnue: Option<NnueState>,
 }
 
 impl Board {
@@ -68,40 +151,165 @@ impl Board {
             valid_en_passant: None,
             half_moves: 0,
             full_moves: 0,
-            pieces: [0.into(); 14],
+            pieces: [[0.into(); 6]; 2],
+            colors: [0.into(); 2],
+            mailbox: [None; 64],
+            hash: 0,
+            pawn_hash: 0,
+            material: [Score::ZERO; 2],
+            piece_squares: [Score::ZERO; 2],
+            phase: 0,
+            nnue: None,
         }
     }
 
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    /// Returns the move number, starting at 1 and incremented after black moves
+    pub fn full_moves(&self) -> u32 {
+        self.full_moves
+    }
+
+    /// Returns the number of half moves since the last capture or pawn move
+    pub fn half_moves(&self) -> u32 {
+        self.half_moves
+    }
+
     /// Return the bitboard of piece kind `piece` for `color`
-    fn piece(&self, piece: Piece, color: Color) -> BitBoard {
-        self.pieces[piece as usize + color as usize]
+    pub fn piece(&self, piece: Piece, color: Color) -> BitBoard {
+        self.pieces[color as usize][piece as usize]
+    }
+
+    /// Returns the piece and its color on `square`, if any
+    pub fn piece_at(&self, square: Square) -> Option<(Piece, Color)> {
+        self.mailbox[square as usize]
+    }
+
+    /// Returns the material of `color` under [MATERIAL]
+    pub fn material(&self, color: Color) -> Score {
+        self.material[color as usize]
+    }
+
+    /// Returns the piece-square table total of `color` under [PIECE_SQUARES]
+    pub fn piece_squares(&self, color: Color) -> Score {
+        self.piece_squares[color as usize]
+    }
+
+    /// Returns the game phase, from 0 in a pawn ending up to [MAX_PHASE]
+    pub fn phase(&self) -> i32 {
+        self.phase.min(MAX_PHASE)
+    }
+
+    /// Places `piece` of `color` on the empty `square`
+    fn put_piece(&mut self, piece: Piece, color: Color, square: Square) {
+        debug_assert!(self.piece_at(square).is_none(), "{square} is occupied");
+        self.pieces[color as usize][piece as usize].set_square(square);
+        self.colors[color as usize].set_square(square);
+        self.mailbox[square as usize] = Some((piece, color));
+        self.hash ^= zobrist::piece_key(piece, color, square);
+        if piece == Piece::Pawn {
+            self.pawn_hash ^= zobrist::piece_key(piece, color, square);
+        }
+        self.update_accumulators(piece, color, square, 1);
+        self.update_nnue(piece, color, square, true);
+    }
+
+    /// Removes and returns the piece on `square`, if any
+    fn remove_piece(&mut self, square: Square) -> Option<(Piece, Color)> {
+        let (piece, color) = self.mailbox[square as usize].take()?;
+        self.pieces[color as usize][piece as usize].clear_square(square);
+        self.colors[color as usize].clear_square(square);
+        self.hash ^= zobrist::piece_key(piece, color, square);
+        if piece == Piece::Pawn {
+            self.pawn_hash ^= zobrist::piece_key(piece, color, square);
+        }
+        self.update_accumulators(piece, color, square, -1);
+        self.update_nnue(piece, color, square, false);
+        Some((piece, color))
+    }
+
+    /// Adds (`sign` 1) or subtracts (`sign` -1) the evaluation terms of `piece` on `square`
+    fn update_accumulators(&mut self, piece: Piece, color: Color, square: Square, sign: i32) {
+        self.material[color as usize] += MATERIAL[piece as usize] * sign;
+        self.piece_squares[color as usize] +=
+            PIECE_SQUARES[piece as usize][relative_square(square, color)] * sign;
+        self.phase += PHASE_WEIGHTS[piece as usize] * sign;
+    }
+
+    /// Evaluates the position with `network` from now on, or the hand-crafted evaluation with `None`
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(NnueState::new);
+        self.refresh_nnue();
+    }
+
+    /// Returns the network the position is evaluated with, if any
+    pub fn network(&self) -> Option<&Arc<Network>> {
+        self.nnue.as_ref().map(|nnue| &nnue.network)
+    }
+
+    /// Returns the NNUE evaluation in centipawns for the side to move, if a network is set
+    pub fn nnue_evaluate(&self) -> Option<i32> {
+        self.nnue
+            .as_ref()
+            .map(|nnue| nnue.evaluate(self.side_to_move))
+    }
+
+    fn update_nnue(&mut self, piece: Piece, color: Color, square: Square, add: bool) {
+        let kings = [
+            self.piece(Piece::King, Color::White).lsb(),
+            self.piece(Piece::King, Color::Black).lsb(),
+        ];
+        if let Some(nnue) = &mut self.nnue {
+            nnue.update(kings, piece, color, square, add);
+        }
+    }
+
+    /// Recomputes the NNUE accumulator halves invalidated by king moves, once a move is complete
+    fn refresh_nnue(&mut self) {
+        if let Some(mut nnue) = self.nnue.take() {
+            nnue.refresh_dirty(self);
+            self.nnue = Some(nnue);
+        }
+    }
+
+    /// Returns a mask of all of `color`'s pieces
+    pub fn pieces_of(&self, color: Color) -> BitBoard {
+        self.colors[color as usize]
+    }
+
+    /// Returns true if `color` has a piece other than pawns and the king
+    ///
+    /// Positions where it doesn't are the typical zugzwang positions, in which having to move
+    /// is a disadvantage.
+    pub fn has_non_pawn_material(&self, color: Color) -> bool {
+        let pawns_and_king = self.piece(Piece::Pawn, color) | self.piece(Piece::King, color);
+        !(self.pieces_of(color) & !pawns_and_king).is_empty()
     }
 
     /// Returns a mask of all white's pieces
     fn white_pieces(&self) -> BitBoard {
-        let mut total = 0.into();
-        for i in 0..6 {
-            total |= self.pieces[i];
-        }
-        total
+        self.pieces_of(Color::White)
     }
 
     /// Returns a mask of all black's pieces
     fn black_pieces(&self) -> BitBoard {
-        let mut total = 0.into();
-        for i in 6..self.pieces.len() {
-            total |= self.pieces[i];
-        }
-        total
+        self.pieces_of(Color::Black)
     }
 
     /// Returns a mask of all friendly pieces of [side_to_move][Self::side_to_move]
     fn friendly(&self) -> BitBoard {
-        self.pieces[self.side_to_move as usize + 6]
+        self.pieces_of(self.side_to_move)
+    }
+
+    /// Returns a mask of all the opponent's pieces of [side_to_move][Self::side_to_move]
+    fn enemy(&self) -> BitBoard {
+        self.pieces_of(self.side_to_move.opposite())
     }
 
     /// Returns a mask of all pieces on the board
-    fn occupied(&self) -> BitBoard {
+    pub fn occupied(&self) -> BitBoard {
         self.white_pieces() | self.black_pieces()
     }
 
@@ -109,6 +317,78 @@ impl Board {
     fn empty(&self) -> BitBoard {
         !self.occupied()
     }
+
+    /// Panics unless the piece bitboards, color masks and mailbox all describe the same position
+    pub fn assert_consistent(&self) {
+        let mut seen = BitBoard::new(0);
+        for color in Color::ALL {
+            let mut all = BitBoard::new(0);
+            for piece in Piece::ALL {
+                let board = self.piece(piece, color);
+                assert!(
+                    (seen & board).is_empty(),
+                    "{color} {piece:?} overlaps another piece"
+                );
+                seen |= board;
+                all |= board;
+            }
+            assert_eq!(all, self.pieces_of(color), "{color} occupancy is stale");
+        }
+        for index in 0..64u8 {
+            let square = Square::from(index);
+            let expected = Color::ALL.into_iter().find_map(|color| {
+                Piece::ALL
+                    .into_iter()
+                    .find(|piece| self.piece(*piece, color).contains(square))
+                    .map(|piece| (piece, color))
+            });
+            assert_eq!(
+                self.piece_at(square),
+                expected,
+                "mailbox disagrees on {square}"
+            );
+        }
+        assert_eq!(self.hash, self.compute_hash(), "zobrist key is stale");
+        assert_eq!(
+            self.pawn_hash,
+            self.compute_pawn_hash(),
+            "pawn zobrist key is stale"
+        );
+        self.assert_accumulators();
+    }
+
+    /// Panics unless the incremental evaluation terms match a full recompute
+    pub fn assert_accumulators(&self) {
+        let mut phase = 0;
+        for color in Color::ALL {
+            let mut material = Score::ZERO;
+            let mut piece_squares = Score::ZERO;
+            for piece in Piece::ALL {
+                for square in self.piece(piece, color) {
+                    material += MATERIAL[piece as usize];
+                    piece_squares += PIECE_SQUARES[piece as usize][relative_square(square, color)];
+                    phase += PHASE_WEIGHTS[piece as usize];
+                }
+            }
+            assert_eq!(self.material(color), material, "{color} material is stale");
+            assert_eq!(
+                self.piece_squares(color),
+                piece_squares,
+                "{color} piece-square total is stale"
+            );
+        }
+        assert_eq!(self.phase, phase, "phase is stale");
+        if let Some(nnue) = &self.nnue {
+            let mut fresh = nnue.accumulator;
+            for color in Color::ALL {
+                fresh.refresh(&nnue.network, self, color);
+            }
+            assert_eq!(
+                nnue.accumulator, fresh,
+                "nnue accumulator doesn't match a refresh"
+            );
+        }
+    }
 }
 
 impl Default for Board {
@@ -117,8 +397,14 @@ impl Default for Board {
     }
 }
 
+/// Prints a diagram of the board followed by the game state.
+///
+/// The alternate form (`{:#}`) prints the raw piece bitboards instead of the diagram.
 impl std::fmt::Display for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if !f.alternate() {
+            writeln!(f, "{}", self.diagram())?;
+        }
         writeln!(f, "side: {}", self.side_to_move)?;
         writeln!(
             f,
@@ -130,22 +416,39 @@ impl std::fmt::Display for Board {
             "black castle: {}, {}",
             self.black_castle_rights.queen, self.black_castle_rights.king
         )?;
-        writeln!(
-            f,
-            "en passant: {}",
-            // ugly solution but Square doesn't have an empty varaint, and it shouldn't
-            if self.valid_en_passant.is_none() {
-                "-".to_string()
-            } else {
-                self.valid_en_passant.unwrap().to_string()
-            }
-        )?;
+        match self.valid_en_passant {
+            Some(square) => writeln!(f, "en passant: {square}")?,
+            None => writeln!(f, "en passant: -")?,
+        }
         writeln!(f, "half moves: {}", self.half_moves)?;
         writeln!(f, "full moves: {}", self.full_moves)?;
-        writeln!(f)?;
-        for board in self.pieces {
-            writeln!(f, "{board}")?;
+        if f.alternate() {
+            for color in Color::ALL {
+                for piece in Piece::ALL {
+                    writeln!(f)?;
+                    writeln!(f, "{color} {piece:?}:")?;
+                    write!(f, "{}", self.piece(piece, color))?;
+                }
+                writeln!(f)?;
+                writeln!(f, "{color} pieces:")?;
+                write!(f, "{}", self.pieces_of(color))?;
+            }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_pawn_material() {
+        let board = Board::from_fen("4k3/pppp4/8/8/8/8/4PP2/4KN2 w - - 0 1").unwrap();
+        assert!(board.has_non_pawn_material(Color::White));
+        assert!(!board.has_non_pawn_material(Color::Black));
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(!board.has_non_pawn_material(Color::White));
+    }
+}
@@ -1,125 +1,476 @@
 use super::{
-    attacks::{KING_ATTACKS, KNIGHT_ATTACKS},
-    bitboard::{BitBoard, FILEA, FILEH, RANK1, RANK4, RANK5, RANK8},
+    attacks::{
+        get_bishop_attacks, get_pawn_attacks, get_rook_attacks, KING_ATTACKS, KNIGHT_ATTACKS,
+    },
+    bitboard::{between, BitBoard, Direction, Square, RANK1, RANK4, RANK5, RANK8},
+    makemove::en_passant_victim,
     Board,
 };
 use crate::{
     board::{Color, Piece},
-    moves::Move,
+    moves::{Move, Promotion},
 };
 
-fn extract_moves(moves: &mut Vec<Move>, mut board: BitBoard, start: u8) {
-    while board != 0 {
-        let index = board.trailing_zeros();
-        moves.push(Move::new(index.into(), start.into(), None));
-        board.clear_bit(index);
-    }
+fn extract_moves(moves: &mut Vec<Move>, board: BitBoard, start: Square) {
+    moves.extend(board.map(|to| Move::new(to, start, None)));
+}
+
+/// Which subset of the pseudo-legal moves to generate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GenKind {
+    /// Every pseudo-legal move
+    All,
+    /// Captures, en passant and queen promotions
+    Captures,
+    /// Everything [GenKind::Captures] leaves out: quiet moves, castling and under-promotions
+    Quiets,
+    /// Moves that might get the king out of check, only valid while in check
+    Evasions,
 }
 
 impl Board {
+    /// Returns every legal move for [side_to_move][Self::side_to_move]
     pub fn gen_moves(&self) -> Vec<Move> {
+        let mut moves = self.gen_pseudo_legal();
+        moves.retain(|mv| self.is_legal(*mv));
+        moves
+    }
+
+    /// Returns the legal move written as `lan` in long algebraic notation, e.g. `e7e8q`
+    pub fn parse_move(&self, lan: &str) -> Option<Move> {
+        self.gen_moves().into_iter().find(|mv| mv.lan_str() == lan)
+    }
+
+    /// Returns every move that follows the piece movement rules,
+    /// including ones that leave the own king in check
+    pub fn gen_pseudo_legal(&self) -> Vec<Move> {
+        let mut moves: Vec<Move> = Vec::new();
+        self.generate(GenKind::All, &mut moves);
+        moves
+    }
+
+    /// Returns the pseudo-legal captures and queen promotions
+    pub fn gen_captures(&self) -> Vec<Move> {
         let mut moves: Vec<Move> = Vec::new();
-        self.gen_king(&mut moves);
-        self.gen_knights(&mut moves);
-        self.gen_pawns(&mut moves);
+        self.generate(GenKind::Captures, &mut moves);
         moves
     }
 
-    fn gen_king(&self, moves: &mut Vec<Move>) {
-        let mut king = self.piece(Piece::King, self.side_to_move);
-        while king != 0 {
-            let index = king.trailing_zeros();
-            let attacks = KING_ATTACKS[index as usize];
-            let valid_attacks = attacks & !self.friendly();
-            extract_moves(moves, valid_attacks, index);
-            king.clear_bit(index);
+    /// Returns true if `mv` could have been generated in this position,
+    /// used to check moves remembered from other positions before playing them
+    pub fn is_pseudo_legal(&self, mv: Move) -> bool {
+        let Some((piece, color)) = self.piece_at(mv.from) else {
+            return false;
+        };
+        if color != self.side_to_move || self.friendly().contains(mv.to) {
+            return false;
+        }
+        if piece != Piece::Pawn && mv.promotion.is_some() {
+            return false;
+        }
+        let occ = self.occupied();
+        match piece {
+            Piece::Knight => KNIGHT_ATTACKS[mv.from as usize].contains(mv.to),
+            Piece::Bishop => get_bishop_attacks(mv.from, occ).contains(mv.to),
+            Piece::Rook => get_rook_attacks(mv.from, occ).contains(mv.to),
+            Piece::Queen => {
+                (get_bishop_attacks(mv.from, occ) | get_rook_attacks(mv.from, occ)).contains(mv.to)
+            }
+            Piece::King if KING_ATTACKS[mv.from as usize].contains(mv.to) => true,
+            Piece::King => {
+                let mut castles = Vec::new();
+                self.gen_castles(&mut castles);
+                castles.contains(&mv)
+            }
+            Piece::Pawn => {
+                let mut pawn_moves = Vec::new();
+                self.gen_pawns(&mut pawn_moves, GenKind::All, !BitBoard::new(0));
+                pawn_moves.contains(&mv)
+            }
         }
     }
 
-    fn gen_knights(&self, moves: &mut Vec<Move>) {
-        let mut knights = self.piece(Piece::Knight, self.side_to_move);
-        while knights != 0 {
-            let index = knights.trailing_zeros();
-            let attacks = KNIGHT_ATTACKS[index as usize];
-            let valid_attacks = attacks & !self.friendly();
-            extract_moves(moves, valid_attacks, index);
-            knights.clear_bit(index);
+    /// Returns true if `mv` captures a piece, including en passant
+    pub fn is_capture(&self, mv: Move) -> bool {
+        self.enemy().contains(mv.to)
+            || self.valid_en_passant == Some(mv.to)
+                && self
+                    .piece_at(mv.from)
+                    .is_some_and(|(piece, _)| piece == Piece::Pawn)
+    }
+
+    /// Adds the pseudo-legal moves of `kind` to `moves`
+    pub fn generate(&self, kind: GenKind, moves: &mut Vec<Move>) {
+        let targets = match kind {
+            GenKind::All => !self.friendly(),
+            GenKind::Captures => self.enemy(),
+            GenKind::Quiets => self.empty(),
+            GenKind::Evasions => self.evasion_targets(),
+        };
+        // the king escapes check by moving, so it isn't bound to the evasion targets
+        let king_targets = if let GenKind::Evasions = kind {
+            !self.friendly()
+        } else {
+            targets
+        };
+
+        self.gen_king(moves, king_targets);
+        if let GenKind::All | GenKind::Quiets = kind {
+            self.gen_castles(moves);
         }
+        self.gen_knights(moves, targets);
+        self.gen_sliders(moves, targets);
+        self.gen_pawns(moves, kind, targets);
     }
 
-    fn gen_pawns(&self, moves: &mut Vec<Move>) {
-        self.gen_pawn_pushes(moves);
-        self.gen_pawn_captures(moves);
+    /// Returns the squares a piece other than the king can move to to resolve a check
+    ///
+    /// That's capturing the checker or blocking its line, neither of which helps against double check.
+    fn evasion_targets(&self) -> BitBoard {
+        let king = self.king_square(self.side_to_move);
+        let mut checkers = self.attackers_to(king, self.occupied()) & self.enemy();
+        match (checkers.pop_lsb(), checkers.is_empty()) {
+            (Some(checker), true) => between(king, checker) | BitBoard::from_square(checker),
+            _ => BitBoard::new(0),
+        }
     }
 
-    fn gen_pawn_pushes(&self, moves: &mut Vec<Move>) {
-        fn extract_moves(moves: &mut Vec<Move>, mut board: BitBoard, offset: u8, color: Color) {
-            while board != 0 {
-                let index = board.trailing_zeros();
-                let start_index = if let Color::White = color {
-                    index - 8 * offset
-                } else {
-                    index + 8 * offset
-                };
-                moves.push(Move::new(index.into(), start_index.into(), None));
-                board.clear_bit(index);
+    /// Returns true if the pseudo-legal `mv` doesn't leave the own king in check
+    ///
+    /// Works out the attacks on the king square after the move instead of playing it, which would
+    /// mean copying the board along with its NNUE accumulator.
+    pub fn is_legal(&self, mv: Move) -> bool {
+        let us = self.side_to_move;
+        let Some((piece, _)) = self.piece_at(mv.from) else {
+            return false;
+        };
+        let king = if piece == Piece::King {
+            mv.to
+        } else {
+            self.king_square(us)
+        };
+        let mut captured = BitBoard::from(mv.to);
+        if self.is_en_passant(piece, mv.to) {
+            captured |= BitBoard::from(en_passant_victim(mv.to, us));
+        }
+        let occ = (self.occupied() & !captured & !BitBoard::from(mv.from)) | BitBoard::from(mv.to);
+        // captured pieces no longer attack, the moved one is ours and never counts
+        (self.attackers_to(king, occ) & self.pieces_of(us.opposite()) & !captured).is_empty()
+    }
+
+    /// Returns true if [side_to_move][Self::side_to_move]'s king is attacked
+    pub fn in_check(&self) -> bool {
+        self.is_attacked(
+            self.king_square(self.side_to_move),
+            self.side_to_move.opposite(),
+        )
+    }
+
+    /// Returns the square of `color`'s king
+    pub fn king_square(&self, color: Color) -> Square {
+        self.piece(Piece::King, color)
+            .lsb()
+            .expect("every position has a king of each color")
+    }
+
+    /// Returns true if any piece of `by` attacks `square`
+    pub fn is_attacked(&self, square: Square, by: Color) -> bool {
+        !(self.attackers_to(square, self.occupied()) & self.pieces_of(by)).is_empty()
+    }
+
+    /// Returns every piece of either color attacking `square`, with sliders blocked by `occ`
+    pub fn attackers_to(&self, square: Square, occ: BitBoard) -> BitBoard {
+        let diagonal = self.piece(Piece::Bishop, Color::White)
+            | self.piece(Piece::Bishop, Color::Black)
+            | self.piece(Piece::Queen, Color::White)
+            | self.piece(Piece::Queen, Color::Black);
+        let straight = self.piece(Piece::Rook, Color::White)
+            | self.piece(Piece::Rook, Color::Black)
+            | self.piece(Piece::Queen, Color::White)
+            | self.piece(Piece::Queen, Color::Black);
+        let knights =
+            self.piece(Piece::Knight, Color::White) | self.piece(Piece::Knight, Color::Black);
+        let kings = self.piece(Piece::King, Color::White) | self.piece(Piece::King, Color::Black);
+
+        // a pawn of one color attacks the squares a pawn of the other color on `square` would
+        (get_pawn_attacks(square, Color::Black) & self.piece(Piece::Pawn, Color::White))
+            | (get_pawn_attacks(square, Color::White) & self.piece(Piece::Pawn, Color::Black))
+            | (KNIGHT_ATTACKS[square as usize] & knights)
+            | (KING_ATTACKS[square as usize] & kings)
+            | (get_bishop_attacks(square, occ) & diagonal)
+            | (get_rook_attacks(square, occ) & straight)
+    }
+
+    fn gen_king(&self, moves: &mut Vec<Move>, targets: BitBoard) {
+        for square in self.piece(Piece::King, self.side_to_move) {
+            let attacks = KING_ATTACKS[square as usize];
+            extract_moves(moves, attacks & targets, square);
+        }
+    }
+
+    fn gen_castles(&self, moves: &mut Vec<Move>) {
+        let (rights, king, king_side, queen_side) = match self.side_to_move {
+            Color::White => (
+                self.white_castle_rights,
+                Square::E1,
+                (Square::H1, Square::F1, Square::G1),
+                (Square::A1, Square::D1, Square::C1),
+            ),
+            Color::Black => (
+                self.black_castle_rights,
+                Square::E8,
+                (Square::H8, Square::F8, Square::G8),
+                (Square::A8, Square::D8, Square::C8),
+            ),
+        };
+        let enemy = self.side_to_move.opposite();
+
+        for (allowed, (rook, through, to)) in [(rights.king, king_side), (rights.queen, queen_side)]
+        {
+            if !allowed
+                || self.piece_at(king) != Some((Piece::King, self.side_to_move))
+                || self.piece_at(rook) != Some((Piece::Rook, self.side_to_move))
+                || !(between(king, rook) & self.occupied()).is_empty()
+            {
+                continue;
+            }
+            // the king may not castle out of, through or into check
+            if [king, through, to]
+                .iter()
+                .any(|square| self.is_attacked(*square, enemy))
+            {
+                continue;
             }
+            moves.push(Move::new(to, king, None));
         }
+    }
+
+    fn gen_knights(&self, moves: &mut Vec<Move>, targets: BitBoard) {
+        for square in self.piece(Piece::Knight, self.side_to_move) {
+            let attacks = KNIGHT_ATTACKS[square as usize];
+            extract_moves(moves, attacks & targets, square);
+        }
+    }
+
+    fn gen_sliders(&self, moves: &mut Vec<Move>, targets: BitBoard) {
+        let queens = self.piece(Piece::Queen, self.side_to_move);
+        let diagonal = self.piece(Piece::Bishop, self.side_to_move) | queens;
+        let straight = self.piece(Piece::Rook, self.side_to_move) | queens;
 
-        if let Color::White = self.side_to_move {
-            let pawns = self.piece(Piece::Pawn, Color::White);
-            let pushes = (pawns << 8) & self.empty() & !RANK8;
-            let double_pushes = (pushes << 8) & self.empty() & RANK4;
-            extract_moves(moves, pushes, 1, Color::White);
-            extract_moves(moves, double_pushes, 2, Color::White);
+        for square in diagonal {
+            let attacks = get_bishop_attacks(square, self.occupied());
+            extract_moves(moves, attacks & targets, square);
+        }
+        for square in straight {
+            let attacks = get_rook_attacks(square, self.occupied());
+            extract_moves(moves, attacks & targets, square);
+        }
+    }
+
+    fn gen_pawns(&self, moves: &mut Vec<Move>, kind: GenKind, targets: BitBoard) {
+        // pushes and captures are masked separately, so only evasions restrict pawn targets
+        let allowed = if let GenKind::Evasions = kind {
+            targets
         } else {
-            let pawns = self.piece(Piece::Pawn, Color::Black);
-            let pushes = (pawns >> 8) & self.empty() & !RANK1;
-            let double_pushes = (pushes >> 8) & self.empty() & RANK5;
-            extract_moves(moves, pushes, 1, Color::Black);
-            extract_moves(moves, double_pushes, 2, Color::Black);
-        }
-    }
-
-    fn gen_pawn_captures(&self, moves: &mut Vec<Move>) {
-        #[derive(Clone, Copy, Debug)]
-        enum Direction {
-            Right,
-            Left,
-        }
-
-        fn extract_moves(moves: &mut Vec<Move>, mut board: BitBoard, dir: Direction, color: Color) {
-            while board != 0 {
-                let index = board.trailing_zeros();
-                let start_index = match (dir, color) {
-                    (Direction::Left, Color::White) => index - 9,
-                    (Direction::Right, Color::White) => index - 7,
-                    (Direction::Left, Color::Black) => index + 9,
-                    (Direction::Right, Color::Black) => index + 7,
-                };
-                moves.push(Move::new(index.into(), start_index.into(), None));
-                board.clear_bit(index);
+            !BitBoard::new(0)
+        };
+        if kind != GenKind::Captures {
+            self.gen_pawn_pushes(moves, allowed);
+        }
+        if kind != GenKind::Quiets {
+            self.gen_pawn_captures(moves, allowed);
+        }
+        for promotion in Promotion::ALL {
+            let wanted = match kind {
+                GenKind::Captures => promotion == Promotion::Queen,
+                GenKind::Quiets => promotion != Promotion::Queen,
+                GenKind::All | GenKind::Evasions => true,
+            };
+            if wanted {
+                self.gen_pawn_promotions(moves, promotion, allowed);
             }
         }
+    }
 
-        if let Color::White = self.side_to_move {
-            let pawns = self.piece(Piece::Pawn, Color::White);
-            // from white's pov
-            let captures_left = (pawns << 9) & self.black_pieces() & !RANK8 & !FILEH;
-            let captures_right = (pawns << 7) & self.black_pieces() & !RANK8 & !FILEA;
-            extract_moves(moves, captures_left, Direction::Left, Color::White);
-            extract_moves(moves, captures_right, Direction::Right, Color::White);
-        } else {
-            let pawns = self.piece(Piece::Pawn, Color::Black);
-            // from black's pov
-            let captures_left = (pawns >> 9) & self.white_pieces() & !RANK1 & !FILEA;
-            let captures_right = (pawns >> 7) & self.white_pieces() & !RANK1 & !FILEH;
-            extract_moves(moves, captures_left, Direction::Left, Color::Black);
-            extract_moves(moves, captures_right, Direction::Right, Color::Black);
+    fn gen_pawn_pushes(&self, moves: &mut Vec<Move>, allowed: BitBoard) {
+        let pawns = self.piece(Piece::Pawn, self.side_to_move);
+        let (forward, offset) = pawn_directions(self.side_to_move)[0];
+        let (double_rank, last_rank) = match self.side_to_move {
+            Color::White => (RANK4, RANK8),
+            Color::Black => (RANK5, RANK1),
+        };
+        let pushes = pawns.shift(forward) & self.empty();
+        let double_pushes = pushes.shift(forward) & self.empty() & double_rank;
+        extract_pawn_moves(moves, pushes & allowed & !last_rank, offset, None);
+        extract_pawn_moves(moves, double_pushes & allowed, 2 * offset, None);
+    }
+
+    fn gen_pawn_captures(&self, moves: &mut Vec<Move>, allowed: BitBoard) {
+        let pawns = self.piece(Piece::Pawn, self.side_to_move);
+        let last_rank = match self.side_to_move {
+            Color::White => RANK8,
+            Color::Black => RANK1,
+        };
+        let mut targets = self.enemy() & allowed;
+        // en passant is left to the legality check, its victim isn't on the target square
+        if let Some(square) = self.valid_en_passant {
+            targets.set_square(square);
+        }
+        for (dir, offset) in &pawn_directions(self.side_to_move)[1..] {
+            let captures = pawns.shift(*dir) & targets & !last_rank;
+            extract_pawn_moves(moves, captures, *offset, None);
+        }
+    }
+
+    fn gen_pawn_promotions(&self, moves: &mut Vec<Move>, promotion: Promotion, allowed: BitBoard) {
+        let pawns = self.piece(Piece::Pawn, self.side_to_move);
+        let [(forward, push_offset), captures @ ..] = pawn_directions(self.side_to_move);
+        let last_rank = match self.side_to_move {
+            Color::White => RANK8,
+            Color::Black => RANK1,
+        };
+        let pushes = pawns.shift(forward) & self.empty() & last_rank & allowed;
+        extract_pawn_moves(moves, pushes, push_offset, Some(promotion));
+        for (dir, offset) in captures {
+            let captures = pawns.shift(dir) & self.enemy() & last_rank & allowed;
+            extract_pawn_moves(moves, captures, offset, Some(promotion));
+        }
+    }
+}
+
+/// Returns the forward, forward-east and forward-west directions of pawns of `color`,
+/// each paired with the change in square index of one step that way
+fn pawn_directions(color: Color) -> [(Direction, i8); 3] {
+    match color {
+        Color::White => [
+            (Direction::North, 8),
+            (Direction::NorthEast, 9),
+            (Direction::NorthWest, 7),
+        ],
+        Color::Black => [
+            (Direction::South, -8),
+            (Direction::SouthEast, -7),
+            (Direction::SouthWest, -9),
+        ],
+    }
+}
+
+/// Adds a pawn move to each square in `board`, starting `offset` squares back by index
+fn extract_pawn_moves(
+    moves: &mut Vec<Move>,
+    board: BitBoard,
+    offset: i8,
+    promotion: Option<Promotion>,
+) {
+    for to in board {
+        let from = (to as i8 - offset) as u8;
+        moves.push(Move::new(to, from.into(), promotion));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the leaf nodes of the legal move tree `depth` plies deep
+    fn perft(board: &mut Board, depth: u32) -> u64 {
+        let moves = board.gen_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mv in moves {
+            let undo = board.make_move(mv);
+            nodes += perft(board, depth - 1);
+            board.unmake_move(mv, undo);
+        }
+        nodes
+    }
+
+    fn assert_perft(fen: &str, expected: &[u64]) {
+        let mut board = Board::from_fen(fen).unwrap();
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(perft(&mut board, depth as u32 + 1), *nodes, "{fen}");
+        }
+    }
+
+    // Reference counts from https://www.chessprogramming.org/Perft_Results
+
+    #[test]
+    fn perft_start_position() {
+        assert_perft(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902, 197281],
+        );
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        assert_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862],
+        );
+    }
+
+    #[test]
+    fn perft_en_passant_and_promotions() {
+        assert_perft(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238],
+        );
+        assert_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467],
+        );
+        assert_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379],
+        );
+    }
+
+    /// Walks the move tree checking that captures and quiets split all moves between them,
+    /// that evasions contain every legal move when in check, and that [Board::is_pseudo_legal]
+    /// agrees with the generator on the moves of the parent position
+    fn check_gen_kinds(board: &mut Board, depth: u32, parent_moves: &[Move]) {
+        board.assert_consistent();
+        let lan = |kind: GenKind, legal_only: bool| {
+            let mut moves = Vec::new();
+            board.generate(kind, &mut moves);
+            let mut moves: Vec<String> = moves
+                .into_iter()
+                .filter(|mv| !legal_only || board.is_legal(*mv))
+                .map(|mv| mv.lan_str())
+                .collect();
+            moves.sort();
+            moves
+        };
+        let mut split = [lan(GenKind::Captures, false), lan(GenKind::Quiets, false)].concat();
+        split.sort();
+        assert_eq!(split, lan(GenKind::All, false));
+        if board.in_check() {
+            assert_eq!(lan(GenKind::Evasions, true), lan(GenKind::All, true));
+        }
+        let moves = board.gen_pseudo_legal();
+        for mv in parent_moves.iter().chain(&moves) {
+            assert_eq!(board.is_pseudo_legal(*mv), moves.contains(mv), "{mv}");
+        }
+
+        if depth > 0 {
+            for mv in board.gen_moves() {
+                let undo = board.make_move(mv);
+                check_gen_kinds(board, depth - 1, &moves);
+                board.unmake_move(mv, undo);
+            }
         }
     }
 
-    fn gen_pawn_promotions(&self, moves: &mut Vec<Move>) -> Vec<Move> {
-        todo!()
+    #[test]
+    fn gen_kinds() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            check_gen_kinds(&mut Board::from_fen(fen).unwrap(), 2, &[]);
+        }
     }
 }
@@ -0,0 +1,24 @@
+use derive_more::derive::{Add, AddAssign, Mul, Neg, Sub, SubAssign, Sum};
+
+/// A pair of middlegame and endgame scores, blended by game phase when evaluating
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Add, AddAssign, Sub, SubAssign, Neg, Mul, Sum,
+)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const ZERO: Score = Score::new(0, 0);
+
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Self { mg, eg }
+    }
+
+    /// Interpolates between the middlegame and endgame score, `phase` runs from
+    /// 0 (bare kings and pawns) to `max_phase` (all pieces on the board)
+    pub fn taper(self, phase: i32, max_phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (max_phase - phase)) / max_phase
+    }
+}
@@ -0,0 +1,164 @@
+use super::{
+    attacks::{get_bishop_attacks, get_rook_attacks},
+    bitboard::{BitBoard, Square, RANK1, RANK8},
+    Board, Color, Piece,
+};
+use crate::moves::Move;
+
+/// Piece values used by the exchange evaluation, indexed by [Piece]
+///
+/// The king is worth more than everything else combined, so it is only ever used for the last capture.
+pub const SEE_VALUES: [i32; 6] = [20_000, 900, 500, 330, 320, 100];
+
+/// Cheapest pieces first, the order attackers join an exchange in
+const CAPTURE_ORDER: [Piece; 6] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+impl Board {
+    /// Static exchange evaluation, the material the side to move wins by playing `mv` when both
+    /// sides keep recapturing on the destination square with their least valuable piece
+    ///
+    /// Either side may stop recapturing once it would lose material. Sliders hidden behind other
+    /// attackers join in as the pieces in front of them are used up, pins are ignored.
+    pub fn see(&self, mv: Move) -> i32 {
+        let Some((mut piece, us)) = self.piece_at(mv.from) else {
+            return 0;
+        };
+        // castling never loses or wins material by itself
+        if piece == Piece::King && (mv.from as u8).abs_diff(mv.to as u8) == 2 {
+            return 0;
+        }
+
+        let mut occ = self.occupied() ^ BitBoard::from(mv.from);
+        let mut gains = [0; 32];
+        gains[0] = match self.piece_at(mv.to) {
+            Some((captured, _)) => SEE_VALUES[captured as usize],
+            None if piece == Piece::Pawn && mv.from.file() != mv.to.file() => {
+                // en passant, the captured pawn sits beside the destination
+                occ ^= BitBoard::from(Square::new(mv.from.rank(), mv.to.file()));
+                SEE_VALUES[Piece::Pawn as usize]
+            }
+            None => 0,
+        };
+        if let Some(promotion) = mv.promotion {
+            piece = promotion.piece();
+            gains[0] += SEE_VALUES[piece as usize] - SEE_VALUES[Piece::Pawn as usize];
+        }
+
+        let diagonal = self.piece(Piece::Bishop, Color::White)
+            | self.piece(Piece::Bishop, Color::Black)
+            | self.piece(Piece::Queen, Color::White)
+            | self.piece(Piece::Queen, Color::Black);
+        let straight = self.piece(Piece::Rook, Color::White)
+            | self.piece(Piece::Rook, Color::Black)
+            | self.piece(Piece::Queen, Color::White)
+            | self.piece(Piece::Queen, Color::Black);
+        let promotes = BitBoard::new(RANK1 | RANK8).contains(mv.to);
+
+        let mut attackers = self.attackers_to(mv.to, occ);
+        let mut side = us.opposite();
+        let mut depth = 0;
+        loop {
+            attackers &= occ;
+            let ours = attackers & self.pieces_of(side);
+            let Some(attacker) = CAPTURE_ORDER
+                .into_iter()
+                .find(|kind| !(ours & self.piece(*kind, side)).is_empty())
+            else {
+                break;
+            };
+            // the king can only take last, when nothing defends the square anymore
+            if attacker == Piece::King && !(attackers & self.pieces_of(side.opposite())).is_empty()
+            {
+                break;
+            }
+
+            depth += 1;
+            gains[depth] = SEE_VALUES[piece as usize] - gains[depth - 1];
+            piece = attacker;
+            if attacker == Piece::Pawn && promotes {
+                piece = Piece::Queen;
+                gains[depth] +=
+                    SEE_VALUES[Piece::Queen as usize] - SEE_VALUES[Piece::Pawn as usize];
+            }
+
+            let from = (ours & self.piece(attacker, side)).lsb().unwrap();
+            occ ^= BitBoard::from(from);
+            // uncover sliders x-raying through the piece that just moved
+            if matches!(attacker, Piece::Pawn | Piece::Bishop | Piece::Queen) {
+                attackers |= get_bishop_attacks(mv.to, occ) & diagonal;
+            }
+            if matches!(attacker, Piece::Rook | Piece::Queen) {
+                attackers |= get_rook_attacks(mv.to, occ) & straight;
+            }
+            side = side.opposite();
+        }
+
+        // each side only continues the exchange if it doesn't lose by doing so
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+        gains[0]
+    }
+
+    /// Returns true if the static exchange evaluation of `mv` is at least `threshold`
+    pub fn see_ge(&self, mv: Move, threshold: i32) -> bool {
+        self.see(mv) >= threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exchanges() {
+        let cases = [
+            // undefended pawn
+            (
+                "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+                "e1e5",
+                100,
+            ),
+            // knight for a pawn
+            (
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3e5",
+                -220,
+            ),
+            // the rook behind joins the exchange
+            ("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5", 100),
+            // without it the first rook is lost
+            ("3rk3/8/8/3p4/8/8/3R4/4K3 w - - 0 1", "d2d5", -400),
+            // en passant
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+            // promotion
+            ("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q", 800),
+            // capturing promotion, the new queen is taken back
+            ("r3k3/1P6/1n6/8/8/8/8/4K3 w - - 0 1", "b7a8q", 400),
+            // the king recaptures, unless the square is defended
+            ("4k3/8/8/8/8/8/2p5/3QK3 b - - 0 1", "c2d1r", 800),
+            ("4k3/8/8/8/8/4n3/2p5/3QK3 b - - 0 1", "c2d1q", 1700),
+            // castling
+            ("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", 0),
+        ];
+        for (fen, lan, expected) in cases {
+            let board = Board::from_fen(fen).unwrap();
+            let mv = board
+                .gen_moves()
+                .into_iter()
+                .find(|mv| mv.lan_str() == lan)
+                .unwrap();
+            assert_eq!(board.see(mv), expected, "{fen} {lan}");
+            assert!(board.see_ge(mv, expected));
+            assert!(!board.see_ge(mv, expected + 1));
+        }
+    }
+}
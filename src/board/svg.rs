@@ -0,0 +1,256 @@
+use std::fmt::Display;
+
+use super::{
+    bitboard::{BitBoard, Square},
+    Board, Color,
+};
+use crate::moves::Move;
+
+const LIGHT_SQUARE: &str = "#f0d9b5";
+const DARK_SQUARE: &str = "#b58863";
+const DEFAULT_HIGHLIGHT: &str = "#cdd26a";
+const DEFAULT_ARROW: &str = "#15781b";
+
+/// A self-contained SVG image of a [Board]
+///
+/// Created with [Board::svg]. The output doesn't reference any external assets,
+/// pieces are drawn with Unicode chess glyphs and every coordinate is an integer,
+/// so rendering the same board twice gives byte-identical output.
+#[derive(Clone, Debug)]
+pub struct SvgBoard<'a> {
+    board: &'a Board,
+    square_size: u32,
+    flipped: bool,
+    coordinates: bool,
+    highlights: Vec<(BitBoard, String)>,
+    arrows: Vec<(Move, String)>,
+}
+
+impl<'a> SvgBoard<'a> {
+    fn new(board: &'a Board) -> Self {
+        Self {
+            board,
+            square_size: 45,
+            flipped: false,
+            coordinates: true,
+            highlights: Vec::new(),
+            arrows: Vec::new(),
+        }
+    }
+
+    /// Set the width and height of a single square in pixels
+    pub fn square_size(mut self, size: u32) -> Self {
+        self.square_size = size;
+        self
+    }
+
+    /// Draw the board from black's point of view
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    /// Draw rank and file labels around the board
+    pub fn coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    /// Highlight every square in `squares` with the default color
+    pub fn highlight(self, squares: BitBoard) -> Self {
+        self.highlight_colored(squares, DEFAULT_HIGHLIGHT)
+    }
+
+    /// Highlight every square in `squares` with `color`, any SVG color string
+    pub fn highlight_colored(mut self, squares: BitBoard, color: &str) -> Self {
+        self.highlights.push((squares, color.to_string()));
+        self
+    }
+
+    /// Draw an arrow from the origin to the destination of `mv` with the default color
+    pub fn arrow(self, mv: Move) -> Self {
+        self.arrow_colored(mv, DEFAULT_ARROW)
+    }
+
+    /// Draw an arrow from the origin to the destination of `mv` with `color`
+    pub fn arrow_colored(mut self, mv: Move, color: &str) -> Self {
+        self.arrows.push((mv, color.to_string()));
+        self
+    }
+
+    fn margin(&self) -> u32 {
+        if self.coordinates {
+            self.square_size / 2
+        } else {
+            0
+        }
+    }
+
+    /// Returns the top left corner of `square` in image coordinates
+    fn square_origin(&self, square: Square) -> (u32, u32) {
+        let rank = square as u32 / 8;
+        let file = square as u32 % 8;
+        let (col, row) = if self.flipped {
+            (7 - file, rank)
+        } else {
+            (file, 7 - rank)
+        };
+        (
+            self.margin() + col * self.square_size,
+            self.margin() + row * self.square_size,
+        )
+    }
+
+    fn square_center(&self, square: Square) -> (u32, u32) {
+        let (x, y) = self.square_origin(square);
+        (x + self.square_size / 2, y + self.square_size / 2)
+    }
+
+    fn write_squares(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let size = self.square_size;
+        for index in 0..64u8 {
+            let square = Square::from(index);
+            let (x, y) = self.square_origin(square);
+            let light = (index / 8 + index % 8) % 2 == 1;
+            let fill = if light { LIGHT_SQUARE } else { DARK_SQUARE };
+            writeln!(
+                f,
+                r#"<rect x="{x}" y="{y}" width="{size}" height="{size}" fill="{fill}"/>"#
+            )?;
+        }
+        for (squares, color) in &self.highlights {
+            for square in *squares {
+                let (x, y) = self.square_origin(square);
+                writeln!(
+                    f,
+                    r#"<rect x="{x}" y="{y}" width="{size}" height="{size}" fill="{color}" fill-opacity="0.6"/>"#
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_coordinates(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let size = self.square_size;
+        let margin = self.margin();
+        let font_size = size / 3;
+        let board_end = margin + 8 * size;
+        for i in 0..8 {
+            let (file, rank) = if self.flipped { (7 - i, i) } else { (i, 7 - i) };
+            let file_char = (b'a' + file as u8) as char;
+            let x = margin + i * size + size / 2;
+            let y = margin + i * size + size / 2;
+            for label_y in [margin / 2, board_end + margin / 2] {
+                writeln!(
+                    f,
+                    r#"<text x="{x}" y="{label_y}" font-size="{font_size}" text-anchor="middle" dominant-baseline="central">{file_char}</text>"#
+                )?;
+            }
+            for label_x in [margin / 2, board_end + margin / 2] {
+                writeln!(
+                    f,
+                    r#"<text x="{label_x}" y="{y}" font-size="{font_size}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                    rank + 1
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_pieces(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let font_size = self.square_size * 4 / 5;
+        for index in 0..64u8 {
+            let square = Square::from(index);
+            let Some((piece, color)) = self.board.piece_at(square) else {
+                continue;
+            };
+            let (x, y) = self.square_center(square);
+            // the filled glyphs are used for both colors so that white pieces aren't see-through
+            let glyph = piece.to_unicode(Color::Black);
+            let (fill, stroke) = if let Color::White = color {
+                ("#ffffff", "#000000")
+            } else {
+                ("#000000", "#ffffff")
+            };
+            writeln!(
+                f,
+                r#"<text x="{x}" y="{y}" font-size="{font_size}" text-anchor="middle" dominant-baseline="central" fill="{fill}" stroke="{stroke}" stroke-width="1">{glyph}</text>"#
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_arrows(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.arrows.is_empty() {
+            return Ok(());
+        }
+        let width = self.square_size / 6;
+        writeln!(f, "<defs>")?;
+        for (i, (_, color)) in self.arrows.iter().enumerate() {
+            writeln!(
+                f,
+                r#"<marker id="arrowhead-{i}" markerWidth="4" markerHeight="4" refX="2" refY="2" orient="auto"><path d="M0,0 L4,2 L0,4 Z" fill="{color}"/></marker>"#
+            )?;
+        }
+        writeln!(f, "</defs>")?;
+        for (i, (mv, color)) in self.arrows.iter().enumerate() {
+            let (x1, y1) = self.square_center(mv.from);
+            let (x2, y2) = self.square_center(mv.to);
+            writeln!(
+                f,
+                r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="{color}" stroke-width="{width}" stroke-opacity="0.8" stroke-linecap="round" marker-end="url(#arrowhead-{i})"/>"#
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for SvgBoard<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let total = 8 * self.square_size + 2 * self.margin();
+        writeln!(
+            f,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{total}" height="{total}" viewBox="0 0 {total} {total}" font-family="DejaVu Sans, Segoe UI Symbol, sans-serif">"#
+        )?;
+        writeln!(
+            f,
+            r##"<rect width="{total}" height="{total}" fill="#ffffff"/>"##
+        )?;
+        self.write_squares(f)?;
+        if self.coordinates {
+            self.write_coordinates(f)?;
+        }
+        self.write_pieces(f)?;
+        self.write_arrows(f)?;
+        writeln!(f, "</svg>")
+    }
+}
+
+impl Board {
+    /// Returns an [SvgBoard] of the board, which can be configured before rendering
+    pub fn svg(&self) -> SvgBoard<'_> {
+        SvgBoard::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::bitboard::Square;
+
+    /// Only update the fixture after checking that the new output renders correctly
+    #[test]
+    fn matches_snapshot() {
+        let board =
+            Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        let svg = board
+            .svg()
+            .square_size(40)
+            .flipped(true)
+            .highlight(BitBoard::from(Square::F3) | BitBoard::from(Square::G1))
+            .arrow(Move::new(Square::B5, Square::F1, None))
+            .to_string();
+        assert_eq!(svg, include_str!("testdata/italian.svg"));
+    }
+}
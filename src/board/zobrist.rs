@@ -0,0 +1,122 @@
+use super::{bitboard::Square, Board, CastleRights, Color, Piece};
+
+/// Random keys hashed into a [Board]'s Zobrist key
+pub struct ZobristKeys {
+    /// Indexed by `[color][piece][square]`
+    pub pieces: [[[u64; 64]; 6]; 2],
+    /// Hashed in while black is to move
+    pub side: u64,
+    /// Indexed by the castling rights as a 4 bit mask, see [castle_index]
+    pub castling: [u64; 16],
+    /// Indexed by the file of the en passant square
+    pub en_passant: [u64; 8],
+}
+
+pub static KEYS: ZobristKeys = gen_keys();
+
+/// SplitMix64, good enough for keys and usable in const eval
+const fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+const fn gen_keys() -> ZobristKeys {
+    let mut state = 0x2545F4914F6CDD1D;
+    let mut keys = ZobristKeys {
+        pieces: [[[0; 64]; 6]; 2],
+        side: 0,
+        castling: [0; 16],
+        en_passant: [0; 8],
+    };
+
+    let mut color = 0;
+    while color < 2 {
+        let mut piece = 0;
+        while piece < 6 {
+            let mut square = 0;
+            while square < 64 {
+                keys.pieces[color][piece][square] = next_random(&mut state);
+                square += 1;
+            }
+            piece += 1;
+        }
+        color += 1;
+    }
+    keys.side = next_random(&mut state);
+    let mut i = 0;
+    while i < 16 {
+        keys.castling[i] = next_random(&mut state);
+        i += 1;
+    }
+    i = 0;
+    while i < 8 {
+        keys.en_passant[i] = next_random(&mut state);
+        i += 1;
+    }
+
+    keys
+}
+
+/// Packs both sides' castling rights into a 4 bit index
+pub fn castle_index(white: CastleRights, black: CastleRights) -> usize {
+    white.king as usize
+        | (white.queen as usize) << 1
+        | (black.king as usize) << 2
+        | (black.queen as usize) << 3
+}
+
+pub fn piece_key(piece: Piece, color: Color, square: Square) -> u64 {
+    KEYS.pieces[color as usize][piece as usize][square as usize]
+}
+
+impl Board {
+    /// Returns the Zobrist key of the position
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the Zobrist key of the pawns alone
+    pub fn pawn_hash(&self) -> u64 {
+        self.pawn_hash
+    }
+
+    /// Computes the pawn key from scratch, the incrementally updated [Board::pawn_hash] must match it
+    pub fn compute_pawn_hash(&self) -> u64 {
+        let mut hash = 0;
+        for color in Color::ALL {
+            for square in self.piece(Piece::Pawn, color) {
+                hash ^= piece_key(Piece::Pawn, color, square);
+            }
+        }
+        hash
+    }
+
+    /// Computes the Zobrist key from scratch, the incrementally updated [Board::hash] must match it
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+        for index in 0..64u8 {
+            let square = Square::from(index);
+            if let Some((piece, color)) = self.piece_at(square) {
+                hash ^= piece_key(piece, color, square);
+            }
+        }
+        hash ^ self.state_key()
+    }
+
+    /// Returns the part of the key that doesn't come from pieces:
+    /// side to move, castling rights and en passant square
+    pub(super) fn state_key(&self) -> u64 {
+        let mut key =
+            KEYS.castling[castle_index(self.white_castle_rights, self.black_castle_rights)];
+        if self.side_to_move == Color::Black {
+            key ^= KEYS.side;
+        }
+        if let Some(square) = self.valid_en_passant {
+            key ^= KEYS.en_passant[square.file() as usize];
+        }
+        key
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    board::{makemove::Undo, Board},
+    eval::nnue::{Network, NetworkError},
+    moves::Move,
+    search::{
+        mate::{MateReport, MateSolver},
+        params::SearchParams,
+        tt::TranspositionTable,
+        SearchLimits, SearchReport, Searcher, ThreadTables, MAX_THREADS,
+    },
+};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Time assumed to get lost per move between the engine and the clock, in milliseconds
+pub const DEFAULT_MOVE_OVERHEAD_MS: u64 = 30;
+/// Largest accepted move overhead, in milliseconds
+pub const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+/// Most lines a search can be asked for, see [Searcher::with_multi_pv]
+pub const MAX_MULTI_PV: usize = 256;
+
+/// Game state and search thread shared by the protocol front ends
+pub struct Engine {
+    board: Board,
+    /// Moves played to reach `board` from the last set position, with what's needed to take them back
+    played: Vec<(Move, Undo)>,
+    /// Zobrist keys of the positions before `board`, oldest first
+    keys: Vec<u64>,
+    tt: Arc<TranspositionTable>,
+    /// Move ordering and pawn tables of each search thread, handed back once a search is done
+    thread_tables: Arc<Mutex<Vec<ThreadTables>>>,
+    network: Option<Arc<Network>>,
+    stop: Arc<AtomicBool>,
+    /// Set while the running search is pondering, see [Engine::start_ponder]
+    ponder: Arc<AtomicBool>,
+    search: Option<JoinHandle<()>>,
+    /// Kept in reserve on every move for communication delays, see
+    /// [TimeControl::overhead](crate::search::time::TimeControl::overhead)
+    move_overhead: Duration,
+    /// Number of best moves each search finds a line for
+    multi_pv: usize,
+    /// Number of threads each search runs on
+    threads: usize,
+    search_params: SearchParams,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self {
+            board: Board::from_fen(START_FEN).unwrap(),
+            played: Vec::new(),
+            keys: Vec::new(),
+            tt: Arc::new(TranspositionTable::default()),
+            thread_tables: Arc::new(Mutex::new(Vec::new())),
+            network: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            ponder: Arc::new(AtomicBool::new(false)),
+            search: None,
+            move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS),
+            multi_pv: 1,
+            threads: 1,
+            search_params: SearchParams::DEFAULT,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Resets to the starting position and forgets everything learned in the last game
+    pub fn new_game(&mut self) {
+        self.stop();
+        self.tt.clear();
+        self.thread_tables.lock().unwrap().clear();
+        self.set_fen(START_FEN);
+    }
+
+    /// Sets up the position `fen` with no moves played, returns false if `fen` is invalid
+    pub fn set_fen(&mut self, fen: &str) -> bool {
+        let Some(mut board) = Board::from_fen(fen) else {
+            return false;
+        };
+        board.set_network(self.network.clone());
+        self.board = board;
+        self.played.clear();
+        self.keys.clear();
+        true
+    }
+
+    /// Plays the move written as `lan`, returns the move or `None` if it isn't legal
+    pub fn play(&mut self, lan: &str) -> Option<Move> {
+        let mv = self.board.parse_move(lan)?;
+        self.play_move(mv);
+        Some(mv)
+    }
+
+    /// Plays the legal move `mv`
+    pub fn play_move(&mut self, mv: Move) {
+        self.keys.push(self.board.hash());
+        let undo = self.board.make_move(mv);
+        self.played.push((mv, undo));
+    }
+
+    /// Takes back the last move, returns false if there is none
+    pub fn undo(&mut self) -> bool {
+        let Some((mv, undo)) = self.played.pop() else {
+            return false;
+        };
+        self.board.unmake_move(mv, undo);
+        self.keys.pop();
+        true
+    }
+
+    /// Reallocates the transposition table with `size_mb` megabytes
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.stop();
+        self.tt = Arc::new(TranspositionTable::new(size_mb));
+    }
+
+    pub fn move_overhead(&self) -> Duration {
+        self.move_overhead
+    }
+
+    pub fn set_move_overhead(&mut self, overhead: Duration) {
+        self.move_overhead = overhead;
+    }
+
+    pub fn set_multi_pv(&mut self, lines: usize) {
+        self.multi_pv = lines.clamp(1, MAX_MULTI_PV);
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.stop();
+        self.threads = threads.clamp(1, MAX_THREADS);
+    }
+
+    pub fn search_params(&self) -> &SearchParams {
+        &self.search_params
+    }
+
+    pub fn set_search_params(&mut self, params: SearchParams) {
+        self.search_params = params;
+    }
+
+    pub fn clear_hash(&mut self) {
+        self.tt.clear();
+    }
+
+    /// Evaluates with the network in the file at `path` from now on, an empty path
+    /// switches back to the hand-crafted evaluation
+    pub fn load_network(&mut self, path: &str) -> Result<(), NetworkError> {
+        self.network = if path.is_empty() {
+            None
+        } else {
+            Some(Arc::new(Network::load(path)?))
+        };
+        self.board.set_network(self.network.clone());
+        Ok(())
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.search
+            .as_ref()
+            .is_some_and(|search| !search.is_finished())
+    }
+
+    /// Searches the current position on a separate thread, calling `report` for every line of
+    /// every iteration and `done` with the final result
+    ///
+    /// With `wait_for_stop` the result is held back until [Engine::stop] is called, as needed
+    /// for infinite analysis.
+    pub fn start_search(
+        &mut self,
+        limits: SearchLimits,
+        wait_for_stop: bool,
+        report: impl FnMut(&SearchReport) + Send + 'static,
+        done: impl FnOnce(SearchReport) + Send + 'static,
+    ) {
+        self.spawn_search(limits, wait_for_stop, false, report, done);
+    }
+
+    /// Searches the current position, the expected one after the opponent's reply, on the
+    /// opponent's time
+    ///
+    /// The clock in `limits` only starts to matter after [Engine::ponder_hit], until then the
+    /// result is held back like in infinite analysis.
+    pub fn start_ponder(
+        &mut self,
+        limits: SearchLimits,
+        report: impl FnMut(&SearchReport) + Send + 'static,
+        done: impl FnOnce(SearchReport) + Send + 'static,
+    ) {
+        self.spawn_search(limits, false, true, report, done);
+    }
+
+    /// Solves the current position for mate within `max_moves` moves on a separate thread,
+    /// calling `done` with the result
+    pub fn start_mate_search(
+        &mut self,
+        max_moves: u8,
+        done: impl FnOnce(MateReport) + Send + 'static,
+    ) {
+        self.stop();
+        self.stop.store(false, Ordering::Relaxed);
+        let mut solver = MateSolver::new(self.board.clone()).with_stop(self.stop.clone());
+        self.search = Some(thread::spawn(move || done(solver.solve(max_moves))));
+    }
+
+    /// Tells the pondering search that the opponent played the expected move, so it goes on
+    /// as a normal timed search
+    pub fn ponder_hit(&self) {
+        self.ponder.store(false, Ordering::Relaxed);
+    }
+
+    fn spawn_search(
+        &mut self,
+        limits: SearchLimits,
+        wait_for_stop: bool,
+        ponder: bool,
+        mut report: impl FnMut(&SearchReport) + Send + 'static,
+        done: impl FnOnce(SearchReport) + Send + 'static,
+    ) {
+        self.stop();
+        self.stop.store(false, Ordering::Relaxed);
+        self.ponder.store(ponder, Ordering::Relaxed);
+        let stop = self.stop.clone();
+        let pondering = self.ponder.clone();
+        let thread_tables = self.thread_tables.clone();
+        let tables = mem::take(&mut *thread_tables.lock().unwrap());
+        let mut searcher = Searcher::with_thread_tables(self.board.clone(), limits, tables)
+            .with_table(self.tt.clone())
+            .with_stop(stop.clone())
+            .with_ponder(pondering.clone())
+            .with_history(self.keys.clone())
+            .with_multi_pv(self.multi_pv)
+            .with_threads(self.threads)
+            .with_params(self.search_params.clone());
+        self.search = Some(thread::spawn(move || {
+            let result = searcher.run(&mut report);
+            *thread_tables.lock().unwrap() = searcher.into_thread_tables();
+            while !stop.load(Ordering::Relaxed)
+                && (wait_for_stop || pondering.load(Ordering::Relaxed))
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            done(result);
+        }));
+    }
+
+    /// Stops the running search, if any, and waits for it to finish
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    /// Waits for the running search, if any, to finish on its own
+    pub fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.join().expect("search thread panicked");
+        }
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc::{self, Receiver, RecvTimeoutError},
+        time::Instant,
+    };
+
+    use super::*;
+    use crate::search::time::{TimeControl, TimeManager};
+
+    /// Starts pondering on `fen`, the result arrives on the returned channel
+    fn ponder(engine: &mut Engine, fen: &str, limits: SearchLimits) -> Receiver<SearchReport> {
+        assert!(engine.set_fen(fen));
+        let (sender, receiver) = mpsc::channel();
+        engine.start_ponder(limits, |_| {}, move |result| sender.send(result).unwrap());
+        receiver
+    }
+
+    /// Limits for a game with `seconds` left on the clock, started now
+    fn clock(seconds: u64) -> SearchLimits {
+        let control = TimeControl {
+            time: Duration::from_secs(seconds),
+            ..Default::default()
+        };
+        SearchLimits {
+            time: Some(TimeManager::new(Instant::now(), &control)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn thread_tables_carry_over() {
+        let mut engine = Engine::new();
+        engine.set_threads(3);
+        let tables = |engine: &Engine| engine.thread_tables.lock().unwrap().len();
+        for _ in 0..2 {
+            let limits = SearchLimits {
+                depth: Some(3),
+                ..Default::default()
+            };
+            engine.start_search(limits, false, |_| {}, |_| {});
+            engine.wait();
+            // one set per thread, handed back for the next search
+            assert_eq!(tables(&engine), 3);
+        }
+        engine.new_game();
+        assert_eq!(tables(&engine), 0);
+    }
+
+    #[test]
+    fn stop_while_pondering() {
+        let mut engine = Engine::new();
+        let results = ponder(&mut engine, START_FEN, clock(60));
+        thread::sleep(Duration::from_millis(50));
+        engine.stop();
+        let result = results.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert!(result.best_move().is_some());
+    }
+
+    #[test]
+    fn ponder_hit_after_finishing_early() {
+        let mut engine = Engine::new();
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..clock(60)
+        };
+        let results = ponder(&mut engine, START_FEN, limits);
+        // done with depth 2 long before, but held back until the ponder hit
+        assert_eq!(
+            results
+                .recv_timeout(Duration::from_millis(200))
+                .unwrap_err(),
+            RecvTimeoutError::Timeout
+        );
+        engine.ponder_hit();
+        let result = results.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(result.depth, 2);
+
+        // the search ends by itself once it finds a mate
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        let results = ponder(&mut engine, fen, clock(60));
+        assert_eq!(
+            results
+                .recv_timeout(Duration::from_millis(200))
+                .unwrap_err(),
+            RecvTimeoutError::Timeout
+        );
+        engine.ponder_hit();
+        let result = results.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(result.best_move(), engine.board().parse_move("h5f7"));
+    }
+
+    #[test]
+    fn ponder_hit_past_the_soft_limit() {
+        let mut engine = Engine::new();
+        let limits = clock(30);
+        let hard = limits.time.as_ref().unwrap().hard_limit();
+        // pondering ignores both limits, wait until past the soft one even if unstable
+        // iterations stretched it, from a quarter of the hard limit to about half of it
+        let results = ponder(&mut engine, START_FEN, limits);
+        let pondering = hard * 6 / 10;
+        assert_eq!(
+            results.recv_timeout(pondering).unwrap_err(),
+            RecvTimeoutError::Timeout
+        );
+        // past the soft limit there's no reason to go on, so the search stops short of the
+        // hard one
+        engine.ponder_hit();
+        let result = results.recv_timeout(hard).unwrap();
+        assert!(result.elapsed < hard, "{:?}", result.elapsed);
+        assert!(result.best_move().is_some());
+    }
+}
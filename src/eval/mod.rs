@@ -0,0 +1,295 @@
+use crate::board::{
+    attacks::{
+        get_bishop_attacks, get_knight_attacks, get_queen_attacks, get_rook_attacks, KING_ATTACKS,
+    },
+    bitboard::{BitBoard, File, Square},
+    psqt::{MAX_PHASE, PHASE_WEIGHTS},
+    score::Score,
+    Board, Color, Piece,
+};
+use params::EvalParams;
+use pawns::{relative_rank, PawnEntry, PawnTable};
+use trace::Term;
+
+pub mod nnue;
+pub mod params;
+pub mod pawns;
+pub mod trace;
+
+/// Material values in centipawns, indexed by [Piece]
+///
+/// These are rough values for move ordering and pruning, the evaluation uses
+/// [MATERIAL][crate::board::psqt::MATERIAL].
+pub const PIECE_VALUES: [i32; 6] = [0, 900, 500, 330, 320, 100];
+
+/// Most danger units counted against a king, keeps the squared penalty in check
+const MAX_KING_DANGER: i32 = 40;
+
+/// Returns the evaluation in centipawns from the side to move's point of view
+///
+/// Positions with a network set are evaluated by it, see [Board::set_network]. Otherwise
+/// material, piece-square tables and phase come from the board's incrementally updated totals
+/// and the pawn structure from `pawn_table`.
+pub fn evaluate(board: &Board, pawn_table: &mut PawnTable) -> i32 {
+    if let Some(score) = board.nnue_evaluate() {
+        return score;
+    }
+    if cfg!(debug_assertions) {
+        board.assert_accumulators();
+    }
+    let params = &EvalParams::DEFAULT;
+    let pawns = pawn_table.probe(board);
+    let score = board.material(Color::White) + board.piece_squares(Color::White)
+        - board.material(Color::Black)
+        - board.piece_squares(Color::Black)
+        + positional(board, params, pawns, Color::White)
+        - positional(board, params, pawns, Color::Black);
+    from_side_to_move(board, score.taper(board.phase(), MAX_PHASE))
+}
+
+/// Turns a score from white's point of view into one from the side to move's
+fn from_side_to_move(board: &Board, score: i32) -> i32 {
+    match board.side_to_move() {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Returns how much material is left, from 0 in a pawn ending up to [MAX_PHASE]
+pub fn phase(board: &Board) -> i32 {
+    let phase = Piece::ALL
+        .into_iter()
+        .map(|piece| {
+            let count = board.piece(piece, Color::White).popcount()
+                + board.piece(piece, Color::Black).popcount();
+            PHASE_WEIGHTS[piece as usize] * count as i32
+        })
+        .sum::<i32>();
+    phase.min(MAX_PHASE)
+}
+
+/// Sums the terms that depend on how pieces interact, which the board doesn't keep track of
+fn positional(board: &Board, params: &EvalParams, pawns: &PawnEntry, color: Color) -> Score {
+    Term::POSITIONAL
+        .into_iter()
+        .map(|term| evaluate_term(board, params, pawns, color, term))
+        .sum()
+}
+
+/// Returns the score of a single evaluation term for `color`'s pieces
+fn evaluate_term(
+    board: &Board,
+    params: &EvalParams,
+    pawns: &PawnEntry,
+    color: Color,
+    term: Term,
+) -> Score {
+    match term {
+        Term::Material => board.material(color),
+        Term::PieceSquares => board.piece_squares(color),
+        Term::Mobility => mobility(board, params, pawns, color),
+        Term::PawnStructure => pawns.scores[color as usize],
+        Term::PassedPawns => passed_pawns(board, params, pawns, color),
+        Term::KingSafety => king_safety(board, params, pawns, color),
+        Term::BishopPair => bishop_pair(board, params, color),
+    }
+}
+
+/// Scores the number of squares each minor and major piece reaches, not counting squares
+/// taken by own pawns and the own king or attacked by enemy pawns
+fn mobility(board: &Board, params: &EvalParams, pawns: &PawnEntry, color: Color) -> Score {
+    let occ = board.occupied();
+    let area = !(board.piece(Piece::Pawn, color)
+        | board.piece(Piece::King, color)
+        | pawns.attacks[color.opposite() as usize]);
+    let reach = |attacks: BitBoard| (attacks & area).popcount() as usize;
+
+    let mut score = Score::ZERO;
+    for square in board.piece(Piece::Knight, color) {
+        score += params.knight_mobility[reach(get_knight_attacks(square, occ))];
+    }
+    for square in board.piece(Piece::Bishop, color) {
+        score += params.bishop_mobility[reach(get_bishop_attacks(square, occ))];
+    }
+    for square in board.piece(Piece::Rook, color) {
+        score += params.rook_mobility[reach(get_rook_attacks(square, occ))];
+    }
+    for square in board.piece(Piece::Queen, color) {
+        score += params.queen_mobility[reach(get_queen_attacks(square, occ))];
+    }
+    score
+}
+
+/// Scores the pawn shield in front of `color`'s king and the enemy pieces attacking next to it
+fn king_safety(board: &Board, params: &EvalParams, pawns: &PawnEntry, color: Color) -> Score {
+    let Some(king) = board.piece(Piece::King, color).lsb() else {
+        return Score::ZERO;
+    };
+    let mut score = Score::ZERO;
+
+    // pawn shelter on the king's file and its neighbours
+    let own_pawns = board.piece(Piece::Pawn, color);
+    let king_rank = king.rank() as usize;
+    let king_file = File::mask(king.file());
+    for file in [king_file.west(), king_file, king_file.east()] {
+        if file.is_empty() {
+            continue;
+        }
+        if !(file & pawns.semi_open_files[color as usize]).is_empty() {
+            score += params.king_open_file;
+            continue;
+        }
+        for square in file & own_pawns {
+            let distance = relative_rank(square.rank() as usize, color) as i32
+                - relative_rank(king_rank, color) as i32;
+            if let 1 | 2 = distance {
+                score += params.pawn_shelter[distance as usize - 1];
+            }
+        }
+    }
+
+    // enemy pieces bearing down on the squares around the king
+    let zone = KING_ATTACKS[king as usize] | BitBoard::from(king);
+    let occ = board.occupied();
+    let enemy = color.opposite();
+    let mut attackers = 0;
+    let mut danger = 0;
+    for piece in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+        for square in board.piece(piece, enemy) {
+            let attacks = match piece {
+                Piece::Queen => get_queen_attacks(square, occ),
+                Piece::Rook => get_rook_attacks(square, occ),
+                Piece::Bishop => get_bishop_attacks(square, occ),
+                _ => get_knight_attacks(square, occ),
+            };
+            let hits = (attacks & zone).popcount() as i32;
+            if hits > 0 {
+                attackers += 1;
+                danger += params.king_attacker_weight[piece as usize] * hits;
+            }
+        }
+    }
+    if attackers >= 2 {
+        let danger = danger.min(MAX_KING_DANGER);
+        score += params.king_danger * (danger * danger / 4);
+    }
+    score
+}
+
+/// Scores how much closer the own king is to each passed pawn's path than the enemy king
+fn passed_pawns(board: &Board, params: &EvalParams, pawns: &PawnEntry, color: Color) -> Score {
+    let (Some(own_king), Some(enemy_king)) = (
+        board.piece(Piece::King, color).lsb(),
+        board.piece(Piece::King, color.opposite()).lsb(),
+    ) else {
+        return Score::ZERO;
+    };
+    let stops = match color {
+        Color::White => pawns.passed[color as usize].north(),
+        Color::Black => pawns.passed[color as usize].south(),
+    };
+    stops
+        .map(|stop| {
+            params.passed_king_distance
+                * (distance(enemy_king, stop) as i32 - distance(own_king, stop) as i32)
+        })
+        .sum()
+}
+
+/// Returns the number of king moves between `a` and `b`
+fn distance(a: Square, b: Square) -> u8 {
+    (a.rank() as u8)
+        .abs_diff(b.rank() as u8)
+        .max((a.file() as u8).abs_diff(b.file() as u8))
+}
+
+fn bishop_pair(board: &Board, params: &EvalParams, color: Color) -> Score {
+    if board.piece(Piece::Bishop, color).popcount() >= 2 {
+        params.bishop_pair
+    } else {
+        Score::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use trace::trace;
+
+    /// Evaluates `board` term by term from scratch, from the side to move's point of view
+    fn from_scratch(board: &Board) -> i32 {
+        from_side_to_move(board, trace(board).score())
+    }
+
+    /// Flips `fen` vertically and swaps the colors, which must not change the evaluation
+    fn mirror_fen(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let swap_case = |s: &str| -> String {
+            s.chars()
+                .map(|c| {
+                    if c.is_ascii_uppercase() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c.to_ascii_uppercase()
+                    }
+                })
+                .collect()
+        };
+        let placement = fields[0].split('/').rev().collect::<Vec<_>>().join("/");
+        let side = if fields[1] == "w" { "b" } else { "w" };
+        let en_passant = match fields[3] {
+            "-" => "-".to_string(),
+            square => {
+                let rank = square.as_bytes()[1] - b'0';
+                format!("{}{}", &square[..1], 9 - rank)
+            }
+        };
+        format!(
+            "{} {side} {} {en_passant} {} {}",
+            swap_case(&placement),
+            swap_case(fields[2]),
+            fields[4],
+            fields[5]
+        )
+    }
+
+    #[test]
+    fn symmetric() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            "2r3k1/5ppp/p3p3/1p1pP3/3P2Q1/P1q3P1/5P1P/2R3K1 b - - 0 30",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            let mirrored = Board::from_fen(&mirror_fen(fen)).unwrap();
+            let pawn_table = &mut PawnTable::new();
+            assert_eq!(
+                evaluate(&board, pawn_table),
+                evaluate(&mirrored, pawn_table),
+                "{fen}"
+            );
+            assert_eq!(evaluate(&board, pawn_table), from_scratch(&board), "{fen}");
+        }
+    }
+
+    #[test]
+    fn pawnless_positions_are_cached_correctly() {
+        // the open files of a pawnless board count for the rooks and against the kings
+        let board = Board::from_fen("rnbq1bnk/8/8/8/8/8/8/RNBQKBN1 w - - 0 1").unwrap();
+        assert_eq!(
+            evaluate(&board, &mut PawnTable::new()),
+            from_scratch(&board)
+        );
+    }
+
+    #[test]
+    fn start_position_is_balanced() {
+        let board =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(evaluate(&board, &mut PawnTable::new()), 0);
+        assert_eq!(phase(&board), MAX_PHASE);
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use super::{feature_index, Network, L1};
+use crate::board::{bitboard::Square, Board, Color, Piece};
+
+/// Feature transformer output of both perspectives, indexed by `[color]`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Accumulator {
+    values: [[i16; L1]; 2],
+}
+
+impl Accumulator {
+    pub fn values(&self, perspective: Color) -> &[i16; L1] {
+        &self.values[perspective as usize]
+    }
+
+    /// Recomputes `perspective`'s half from every piece on `board`
+    pub fn refresh(&mut self, network: &Network, board: &Board, perspective: Color) {
+        let values = &mut self.values[perspective as usize];
+        values.copy_from_slice(network.ft_biases());
+        let Some(king) = board.piece(Piece::King, perspective).lsb() else {
+            return;
+        };
+        for color in Color::ALL {
+            for piece in Piece::ALL.into_iter().filter(|piece| *piece != Piece::King) {
+                for square in board.piece(piece, color) {
+                    let feature = feature_index(perspective, king, piece, color, square);
+                    add_weights(values, network.ft_weights(feature));
+                }
+            }
+        }
+    }
+}
+
+/// A board's view of its network, kept up to date as pieces move
+#[derive(Clone, Debug)]
+pub struct NnueState {
+    pub network: Arc<Network>,
+    pub accumulator: Accumulator,
+    /// Perspectives whose king moved, their half is recomputed once the move is complete
+    pub dirty: [bool; 2],
+}
+
+impl NnueState {
+    pub fn new(network: Arc<Network>) -> Self {
+        Self {
+            network,
+            accumulator: Accumulator {
+                values: [[0; L1]; 2],
+            },
+            dirty: [true; 2],
+        }
+    }
+
+    /// Adds (`add` true) or removes `piece` of `color` on `square` from every perspective that
+    /// isn't dirty, `kings` are the king squares indexed by `[color]`
+    pub fn update(
+        &mut self,
+        kings: [Option<Square>; 2],
+        piece: Piece,
+        color: Color,
+        square: Square,
+        add: bool,
+    ) {
+        if piece == Piece::King {
+            // every feature of this perspective is relative to its king
+            self.dirty[color as usize] = true;
+            return;
+        }
+        for perspective in Color::ALL {
+            let Some(king) = kings[perspective as usize] else {
+                self.dirty[perspective as usize] = true;
+                continue;
+            };
+            if self.dirty[perspective as usize] {
+                continue;
+            }
+            let weights =
+                self.network
+                    .ft_weights(feature_index(perspective, king, piece, color, square));
+            let values = &mut self.accumulator.values[perspective as usize];
+            if add {
+                add_weights(values, weights);
+            } else {
+                sub_weights(values, weights);
+            }
+        }
+    }
+
+    /// Recomputes the halves of the dirty perspectives
+    pub fn refresh_dirty(&mut self, board: &Board) {
+        for perspective in Color::ALL {
+            if self.dirty[perspective as usize] {
+                self.accumulator.refresh(&self.network, board, perspective);
+                self.dirty[perspective as usize] = false;
+            }
+        }
+    }
+
+    /// Returns the network's evaluation in centipawns for `side_to_move`
+    pub fn evaluate(&self, side_to_move: Color) -> i32 {
+        debug_assert!(!self.dirty.contains(&true), "accumulator is out of date");
+        self.network.evaluate(&self.accumulator, side_to_move)
+    }
+}
+
+// plain loops over int16, simple enough for the compiler to vectorize on its own
+
+fn add_weights(values: &mut [i16; L1], weights: &[i16]) {
+    for (value, &weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_add(weight);
+    }
+}
+
+fn sub_weights(values: &mut [i16; L1], weights: &[i16]) {
+    for (value, &weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_sub(weight);
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs, io,
+    path::Path,
+};
+
+use crate::board::{bitboard::Square, Color, Piece};
+use accumulator::Accumulator;
+
+pub mod accumulator;
+mod simd;
+
+/// Input features per perspective: every king square times every non-king piece on every square
+pub const INPUTS: usize = 64 * 10 * 64;
+/// Size of the feature transformer output, per perspective
+pub const L1: usize = 256;
+/// Size of the hidden layer
+pub const L2: usize = 32;
+
+/// Activations are clipped to `0..=ACTIVATION_MAX` before feeding the next layer
+const ACTIVATION_MAX: i32 = 127;
+/// Hidden layer sums carry this many extra bits from the int8 weights
+const HIDDEN_SHIFT: u32 = 6;
+/// The output divided by this is the score in centipawns
+const OUTPUT_SCALE: i32 = 16;
+
+const MAGIC: &[u8; 4] = b"CBNN";
+const VERSION: u32 = 1;
+
+/// A quantized HalfKP network: a feature transformer from piece placements relative to each
+/// king to [L1] int16 values per perspective, then an int8 dense layer of [L2] and the output
+///
+/// The file format is little-endian: the magic `CBNN`, the version, the [INPUTS], [L1] and
+/// [L2] sizes as u32, then feature transformer biases (i16) and weights (i16, feature major),
+/// hidden biases (i32) and weights (i8, neuron major), output bias (i32) and weights (i8).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Network {
+    ft_biases: Vec<i16>,
+    ft_weights: Vec<i16>,
+    hidden_biases: Vec<i32>,
+    hidden_weights: Vec<i8>,
+    output_bias: i32,
+    output_weights: Vec<i8>,
+}
+
+/// Why a network file couldn't be loaded
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    /// The layer sizes in the file differ from the ones compiled in
+    WrongShape,
+    /// The file ended early or has data left over
+    WrongLength,
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::Io(err) => write!(f, "can't read network: {err}"),
+            NetworkError::BadMagic => write!(f, "not a network file"),
+            NetworkError::UnsupportedVersion(version) => {
+                write!(f, "unsupported network version {version}")
+            }
+            NetworkError::WrongShape => write!(f, "network layer sizes don't match"),
+            NetworkError::WrongLength => write!(f, "network file has the wrong length"),
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+impl From<io::Error> for NetworkError {
+    fn from(err: io::Error) -> Self {
+        NetworkError::Io(err)
+    }
+}
+
+impl Network {
+    /// Reads a network file from disk
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NetworkError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NetworkError> {
+        let mut reader = Reader { bytes };
+        if reader.take(4)? != MAGIC {
+            return Err(NetworkError::BadMagic);
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(NetworkError::UnsupportedVersion(version));
+        }
+        let shape = [reader.u32()?, reader.u32()?, reader.u32()?];
+        if shape != [INPUTS as u32, L1 as u32, L2 as u32] {
+            return Err(NetworkError::WrongShape);
+        }
+
+        let network = Network {
+            ft_biases: reader.i16s(L1)?,
+            ft_weights: reader.i16s(INPUTS * L1)?,
+            hidden_biases: reader.i32s(L2)?,
+            hidden_weights: reader.i8s(L2 * 2 * L1)?,
+            output_bias: reader.i32s(1)?[0],
+            output_weights: reader.i8s(L2)?,
+        };
+        if !reader.bytes.is_empty() {
+            return Err(NetworkError::WrongLength);
+        }
+        Ok(network)
+    }
+
+    /// Serializes the network in the format [Network::from_bytes] reads
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        for value in [VERSION, INPUTS as u32, L1 as u32, L2 as u32] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend(self.ft_biases.iter().flat_map(|v| v.to_le_bytes()));
+        bytes.extend(self.ft_weights.iter().flat_map(|v| v.to_le_bytes()));
+        bytes.extend(self.hidden_biases.iter().flat_map(|v| v.to_le_bytes()));
+        bytes.extend(self.hidden_weights.iter().flat_map(|v| v.to_le_bytes()));
+        bytes.extend(self.output_bias.to_le_bytes());
+        bytes.extend(self.output_weights.iter().flat_map(|v| v.to_le_bytes()));
+        bytes
+    }
+
+    /// Returns the feature transformer biases, the accumulator of an empty board
+    pub fn ft_biases(&self) -> &[i16] {
+        &self.ft_biases
+    }
+
+    /// Returns the feature transformer weights of input `feature`
+    pub fn ft_weights(&self, feature: usize) -> &[i16] {
+        &self.ft_weights[feature * L1..(feature + 1) * L1]
+    }
+
+    /// Runs the layers after the feature transformer, returning centipawns for `side_to_move`
+    pub fn evaluate(&self, accumulator: &Accumulator, side_to_move: Color) -> i32 {
+        // the side to move's half comes first, so the network knows whose turn it is
+        let mut inputs = [0u8; 2 * L1];
+        let perspectives = [side_to_move, side_to_move.opposite()];
+        for (half, perspective) in inputs.chunks_exact_mut(L1).zip(perspectives) {
+            for (input, &value) in half.iter_mut().zip(accumulator.values(perspective)) {
+                *input = (value as i32).clamp(0, ACTIVATION_MAX) as u8;
+            }
+        }
+
+        let mut hidden = [0u8; L2];
+        for (neuron, output) in hidden.iter_mut().enumerate() {
+            let weights = &self.hidden_weights[neuron * 2 * L1..(neuron + 1) * 2 * L1];
+            let sum = self.hidden_biases[neuron] + simd::dot(weights, &inputs);
+            *output = (sum >> HIDDEN_SHIFT).clamp(0, ACTIVATION_MAX) as u8;
+        }
+
+        (self.output_bias + simd::dot(&self.output_weights, &hidden)) / OUTPUT_SCALE
+    }
+}
+
+/// Returns the input index of `piece` of `color` on `square` seen from `perspective`,
+/// whose king stands on `king`
+///
+/// Black's perspective mirrors the board vertically and swaps the colors, so both
+/// perspectives share the same weights.
+pub fn feature_index(
+    perspective: Color,
+    king: Square,
+    piece: Piece,
+    color: Color,
+    square: Square,
+) -> usize {
+    debug_assert!(piece != Piece::King, "kings aren't input features");
+    let orient = |square: Square| match perspective {
+        Color::White => square as usize,
+        Color::Black => square as usize ^ 56,
+    };
+    // Queen is 1 and Pawn is 5 in [Piece], the king doesn't get a slot
+    let kind = (piece as usize - 1) * 2 + (color != perspective) as usize;
+    (orient(king) * 10 + kind) * 64 + orient(square)
+}
+
+/// Little-endian cursor over the bytes of a network file
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], NetworkError> {
+        if self.bytes.len() < count {
+            return Err(NetworkError::WrongLength);
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, NetworkError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i8s(&mut self, count: usize) -> Result<Vec<i8>, NetworkError> {
+        Ok(self.take(count)?.iter().map(|&b| b as i8).collect())
+    }
+
+    fn i16s(&mut self, count: usize) -> Result<Vec<i16>, NetworkError> {
+        Ok(self
+            .take(count * 2)?
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect())
+    }
+
+    fn i32s(&mut self, count: usize) -> Result<Vec<i32>, NetworkError> {
+        Ok(self
+            .take(count * 4)?
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, LazyLock};
+
+    use super::*;
+    use crate::board::Board;
+
+    /// A network with small random weights, enough to make every feature matter
+    fn random_network(mut seed: u64) -> Network {
+        let mut next = move || {
+            // xorshift64
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        let mut small = |range: i64| (next() % (2 * range as u64 + 1)) as i64 - range;
+        Network {
+            ft_biases: (0..L1).map(|_| small(64) as i16).collect(),
+            ft_weights: (0..INPUTS * L1).map(|_| small(32) as i16).collect(),
+            hidden_biases: (0..L2).map(|_| small(1024) as i32).collect(),
+            hidden_weights: (0..L2 * 2 * L1).map(|_| small(127) as i8).collect(),
+            output_bias: small(1024) as i32,
+            output_weights: (0..L2).map(|_| small(127) as i8).collect(),
+        }
+    }
+
+    static NETWORK: LazyLock<Arc<Network>> =
+        LazyLock::new(|| Arc::new(random_network(0x1234_5678_9ABC_DEF1)));
+
+    #[test]
+    fn file_round_trip() {
+        let path = std::env::temp_dir().join(format!("chessbot-test-{}.nnue", std::process::id()));
+        fs::write(&path, NETWORK.to_bytes()).unwrap();
+        let loaded = Network::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), **NETWORK);
+
+        let mut bytes = NETWORK.to_bytes();
+        bytes.pop();
+        assert!(matches!(
+            Network::from_bytes(&bytes),
+            Err(NetworkError::WrongLength)
+        ));
+        bytes[0] = b'X';
+        assert!(matches!(
+            Network::from_bytes(&bytes),
+            Err(NetworkError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn simd_matches_scalar() {
+        let weights: Vec<i8> = (0..2 * L1).map(|i| (i * 37 % 256) as u8 as i8).collect();
+        let inputs: Vec<u8> = (0..2 * L1).map(|i| (i * 11 % 128) as u8).collect();
+        assert_eq!(
+            simd::dot(&weights, &inputs),
+            simd::dot_scalar(&weights, &inputs)
+        );
+    }
+
+    /// Walks the move tree, checking the incremental accumulator against a refresh at every node
+    fn walk(board: &mut Board, depth: u32) {
+        board.assert_consistent();
+        if depth == 0 {
+            return;
+        }
+        for mv in board.gen_moves() {
+            let undo = board.make_move(mv);
+            walk(board, depth - 1);
+            board.unmake_move(mv, undo);
+        }
+    }
+
+    #[test]
+    fn incremental_matches_refresh() {
+        let fens = [
+            // castling both ways and en passant
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            // promotions with and without captures
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+        for fen in fens {
+            let mut board = Board::from_fen(fen).unwrap();
+            board.set_network(Some(NETWORK.clone()));
+            walk(&mut board, 2);
+
+            // after walking the tree the board evaluates like a freshly loaded one
+            let mut fresh = Board::from_fen(fen).unwrap();
+            fresh.set_network(Some(NETWORK.clone()));
+            assert_eq!(board.nnue_evaluate(), fresh.nnue_evaluate());
+        }
+    }
+}
@@ -0,0 +1,48 @@
+/// Returns the dot product of int8 `weights` and `inputs` in `0..=127`
+///
+/// Uses AVX2 when the `simd` feature is enabled and the CPU supports it.
+pub fn dot(weights: &[i8], inputs: &[u8]) -> i32 {
+    debug_assert_eq!(weights.len(), inputs.len());
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if inputs.len().is_multiple_of(32) && std::arch::is_x86_feature_detected!("avx2") {
+        // SAFETY: the CPU supports AVX2 and the slices have the same length, a multiple of 32
+        return unsafe { dot_avx2(weights, inputs) };
+    }
+    dot_scalar(weights, inputs)
+}
+
+pub fn dot_scalar(weights: &[i8], inputs: &[u8]) -> i32 {
+    weights
+        .iter()
+        .zip(inputs)
+        .map(|(&weight, &input)| weight as i32 * input as i32)
+        .sum()
+}
+
+/// # Safety
+///
+/// The CPU must support AVX2, and both slices must have the same length, a multiple of 32.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+unsafe fn dot_avx2(weights: &[i8], inputs: &[u8]) -> i32 {
+    use std::arch::x86_64::*;
+
+    let ones = _mm256_set1_epi16(1);
+    let mut sum = _mm256_setzero_si256();
+    for (weights, inputs) in weights.chunks_exact(32).zip(inputs.chunks_exact(32)) {
+        // SAFETY: both chunks are 32 bytes long
+        let (w, x) = unsafe {
+            (
+                _mm256_loadu_si256(weights.as_ptr().cast()),
+                _mm256_loadu_si256(inputs.as_ptr().cast()),
+            )
+        };
+        // inputs are at most 127, so the pairwise int16 sums can't saturate
+        let pairs = _mm256_maddubs_epi16(x, w);
+        sum = _mm256_add_epi32(sum, _mm256_madd_epi16(pairs, ones));
+    }
+    let mut lanes = [0i32; 8];
+    // SAFETY: `lanes` is 32 bytes long
+    unsafe { _mm256_storeu_si256(lanes.as_mut_ptr().cast(), sum) };
+    lanes.iter().sum()
+}
@@ -0,0 +1,104 @@
+use crate::board::score::Score;
+
+const fn s(mg: i32, eg: i32) -> Score {
+    Score::new(mg, eg)
+}
+
+/// Every weight of the positional evaluation terms, so that they can be tuned as one set
+///
+/// Per piece values are indexed by [Piece][crate::board::Piece]. Material and piece-square
+/// weights are [MATERIAL][crate::board::psqt::MATERIAL] and
+/// [PIECE_SQUARES][crate::board::psqt::PIECE_SQUARES], which the board keeps running totals of.
+#[derive(Clone, Debug)]
+pub struct EvalParams {
+    /// Bonus by number of reachable squares in the mobility area
+    pub knight_mobility: [Score; 9],
+    pub bishop_mobility: [Score; 14],
+    pub rook_mobility: [Score; 15],
+    pub queen_mobility: [Score; 28],
+    /// Penalty for each pawn with a friendly pawn in front of it on the same file
+    pub doubled_pawn: Score,
+    /// Penalty for each pawn without friendly pawns on the neighbouring files
+    pub isolated_pawn: Score,
+    /// Penalty for each pawn that can't be defended by a pawn and can't safely advance
+    pub backward_pawn: Score,
+    /// Bonus for each pawn defended by a friendly pawn
+    pub supported_pawn: Score,
+    /// Bonus for passed pawns by rank, counted from the pawn's own side
+    pub passed_pawn: [Score; 8],
+    /// Bonus for pawns on a file without enemy pawns that have at least as many friendly pawns
+    /// next to them as enemy pawns guarding their path, by rank
+    pub candidate_passer: [Score; 8],
+    /// Bonus per square the enemy king is further from a passed pawn's stop square than the own king
+    pub passed_king_distance: Score,
+    /// Bonus for each friendly pawn shielding the king, one and two ranks in front of it
+    pub pawn_shelter: [Score; 2],
+    /// Penalty for each file next to or on the king's file without a friendly pawn
+    pub king_open_file: Score,
+    /// How much each enemy piece attacking a square next to the king adds to the danger, per square
+    pub king_attacker_weight: [i32; 6],
+    /// Applied once per squared unit of danger, only when two or more pieces attack
+    pub king_danger: Score,
+    pub bishop_pair: Score,
+}
+
+impl EvalParams {
+    #[rustfmt::skip]
+    pub const DEFAULT: EvalParams = EvalParams {
+        knight_mobility: [
+            s(-31, -32), s(-26, -22), s(-6, -12), s(-2, -6), s(1, 2), s(6, 4), s(11, 6),
+            s(14, 8), s(16, 10),
+        ],
+        bishop_mobility: [
+            s(-24, -23), s(-10, -9), s(8, -1), s(13, 5), s(19, 9), s(25, 16), s(27, 21),
+            s(31, 22), s(31, 26), s(34, 29), s(40, 31), s(40, 34), s(45, 35), s(49, 38),
+        ],
+        rook_mobility: [
+            s(-30, -31), s(-10, -6), s(1, 9), s(1, 15), s(1, 28), s(5, 39), s(11, 41),
+            s(15, 48), s(20, 53), s(20, 55), s(20, 63), s(24, 65), s(28, 67), s(28, 67),
+            s(31, 68),
+        ],
+        queen_mobility: [
+            s(-15, -19), s(-6, -12), s(-4, -2), s(-4, 7), s(10, 16), s(11, 22), s(11, 23),
+            s(17, 30), s(19, 31), s(26, 38), s(32, 38), s(32, 40), s(32, 48), s(33, 50),
+            s(33, 52), s(33, 53), s(36, 54), s(36, 56), s(38, 58), s(39, 60), s(46, 60),
+            s(54, 67), s(54, 67), s(54, 68), s(55, 72), s(57, 72), s(57, 76), s(58, 87),
+        ],
+        doubled_pawn: s(-10, -20),
+        isolated_pawn: s(-6, -12),
+        backward_pawn: s(-8, -10),
+        supported_pawn: s(6, 4),
+        passed_pawn: [
+            s(0, 0),
+            s(2, 5),
+            s(4, 8),
+            s(8, 15),
+            s(15, 30),
+            s(25, 50),
+            s(40, 80),
+            s(0, 0),
+        ],
+        candidate_passer: [
+            s(0, 0),
+            s(1, 2),
+            s(2, 4),
+            s(4, 8),
+            s(8, 15),
+            s(12, 25),
+            s(0, 0),
+            s(0, 0),
+        ],
+        passed_king_distance: s(0, 4),
+        pawn_shelter: [s(12, 0), s(6, 0)],
+        king_open_file: s(-15, 0),
+        king_attacker_weight: [0, 5, 3, 2, 2, 0],
+        king_danger: s(-1, 0),
+        bishop_pair: s(30, 50),
+    };
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -0,0 +1,203 @@
+use super::params::EvalParams;
+use crate::board::{
+    bitboard::{BitBoard, File},
+    score::Score,
+    Board, Color, Piece,
+};
+
+/// Number of entries in a [PawnTable], a power of two
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+/// The pawn structure evaluation of a position along with the bitboards other terms reuse
+///
+/// Everything here only depends on the pawns, so it is shared by all positions with the same
+/// [Board::pawn_hash]. Per color values are indexed by `[color]`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PawnEntry {
+    key: u64,
+    /// Pawn structure score of each color
+    pub scores: [Score; 2],
+    /// Passed pawns
+    pub passed: [BitBoard; 2],
+    /// Squares attacked by pawns
+    pub attacks: [BitBoard; 2],
+    /// Files without pawns of that color
+    pub semi_open_files: [BitBoard; 2],
+}
+
+impl PawnEntry {
+    /// Evaluates the pawn structure of `board` from scratch
+    pub fn compute(board: &Board, params: &EvalParams) -> Self {
+        let mut entry = PawnEntry {
+            key: board.pawn_hash(),
+            ..Default::default()
+        };
+        for color in Color::ALL {
+            let pawns = board.piece(Piece::Pawn, color);
+            entry.attacks[color as usize] = pawn_attacks(pawns, color);
+            entry.semi_open_files[color as usize] = !(fill_north(pawns) | fill_south(pawns));
+        }
+        for color in Color::ALL {
+            entry.scores[color as usize] = structure(board, params, &mut entry, color);
+        }
+        entry
+    }
+}
+
+/// A cache of pawn structure evaluations keyed by [Board::pawn_hash]
+///
+/// Entries are always computed with [EvalParams::DEFAULT].
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl PawnTable {
+    pub fn new() -> Self {
+        // an empty entry would pass for the pawnless position, whose key is 0
+        let empty = PawnEntry {
+            key: !0,
+            ..Default::default()
+        };
+        Self {
+            entries: vec![empty; PAWN_TABLE_SIZE],
+        }
+    }
+
+    /// Returns the pawn evaluation of `board`, computing and caching it on a miss
+    pub fn probe(&mut self, board: &Board) -> &PawnEntry {
+        let key = board.pawn_hash();
+        let index = key as usize & (PAWN_TABLE_SIZE - 1);
+        let entry = &mut self.entries[index];
+        if entry.key != key {
+            *entry = PawnEntry::compute(board, &EvalParams::DEFAULT);
+        }
+        entry
+    }
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the squares attacked by `pawns` of `color`
+pub fn pawn_attacks(pawns: BitBoard, color: Color) -> BitBoard {
+    match color {
+        Color::White => pawns.north_east() | pawns.north_west(),
+        Color::Black => pawns.south_east() | pawns.south_west(),
+    }
+}
+
+/// Returns the squares in front of `pawns` on their files, as seen by `color`
+pub fn front_span(pawns: BitBoard, color: Color) -> BitBoard {
+    match color {
+        Color::White => fill_north(pawns.north()),
+        Color::Black => fill_south(pawns.south()),
+    }
+}
+
+fn fill_north(board: BitBoard) -> BitBoard {
+    let mut bits = board.bits();
+    bits |= bits << 8;
+    bits |= bits << 16;
+    bits |= bits << 32;
+    BitBoard::new(bits)
+}
+
+fn fill_south(board: BitBoard) -> BitBoard {
+    let mut bits = board.bits();
+    bits |= bits >> 8;
+    bits |= bits >> 16;
+    bits |= bits >> 32;
+    BitBoard::new(bits)
+}
+
+/// Scores the pawn structure of `color` and records its passed pawns in `entry`
+fn structure(board: &Board, params: &EvalParams, entry: &mut PawnEntry, color: Color) -> Score {
+    let own = board.piece(Piece::Pawn, color);
+    let enemy = board.piece(Piece::Pawn, color.opposite());
+    let enemy_attacks = entry.attacks[color.opposite() as usize];
+
+    let mut score = Score::ZERO;
+    for square in own {
+        let pawn = BitBoard::from(square);
+        let file = File::mask(square.file());
+        let neighbours = file.east() | file.west();
+        let ahead = front_span(pawn, color);
+        let level_or_behind = pawn | front_span(pawn, color.opposite());
+        let rank = relative_rank(square.rank() as usize, color);
+
+        if !(ahead & own).is_empty() {
+            score += params.doubled_pawn;
+        }
+        if !(entry.attacks[color as usize] & pawn).is_empty() {
+            score += params.supported_pawn;
+        }
+        if (neighbours & own).is_empty() {
+            score += params.isolated_pawn;
+        } else {
+            // no neighbour on the same rank or behind can ever defend it, and it can't advance
+            let supporters = level_or_behind.east() | level_or_behind.west();
+            let stop = match color {
+                Color::White => pawn.north(),
+                Color::Black => pawn.south(),
+            };
+            if (supporters & own).is_empty() && !(stop & enemy_attacks).is_empty() {
+                score += params.backward_pawn;
+            }
+        }
+
+        // only the front pawn of a doubled pair counts as passed
+        let sentries = enemy & (ahead.east() | ahead.west());
+        if (ahead & (own | enemy)).is_empty() {
+            if sentries.is_empty() {
+                entry.passed[color as usize] |= pawn;
+                score += params.passed_pawn[rank];
+            } else {
+                // could become passed by trading off the pawns in the way
+                let helpers = own & (level_or_behind.east() | level_or_behind.west());
+                if helpers.popcount() >= sentries.popcount() {
+                    score += params.candidate_passer[rank];
+                }
+            }
+        }
+    }
+    score
+}
+
+/// Returns `rank` counted from `color`'s side of the board
+pub fn relative_rank(rank: usize, color: Color) -> usize {
+    match color {
+        Color::White => rank,
+        Color::Black => 7 - rank,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::bitboard::Square;
+
+    #[test]
+    fn passed_pawns_and_cache() {
+        // d5 and c3 are passed, c2 is doubled behind c3, black's a7 pawn is passed
+        let board = Board::from_fen("4k3/p7/7p/3P4/8/2P5/2P3P1/4K3 w - - 0 1").unwrap();
+        let entry = PawnEntry::compute(&board, &EvalParams::DEFAULT);
+        assert_eq!(
+            entry.passed[Color::White as usize],
+            BitBoard::from(Square::D5) | BitBoard::from(Square::C3)
+        );
+        assert_eq!(
+            entry.passed[Color::Black as usize],
+            BitBoard::from(Square::A7)
+        );
+        assert!(entry.semi_open_files[Color::White as usize].contains(Square::A1));
+        assert!(!entry.semi_open_files[Color::White as usize].contains(Square::C8));
+
+        let mut table = PawnTable::new();
+        assert_eq!(table.probe(&board).scores, entry.scores);
+        // a second probe is served from the cache
+        assert_eq!(table.probe(&board).scores, entry.scores);
+    }
+}
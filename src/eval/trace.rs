@@ -0,0 +1,162 @@
+use std::fmt::{self, Display};
+
+use super::{evaluate_term, params::EvalParams, pawns::PawnEntry, phase};
+use crate::board::{psqt::MAX_PHASE, score::Score, Board, Color};
+
+/// One named part of the evaluation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Term {
+    Material,
+    PieceSquares,
+    Mobility,
+    PawnStructure,
+    PassedPawns,
+    KingSafety,
+    BishopPair,
+}
+
+impl Term {
+    pub const ALL: [Term; 7] = [
+        Term::Material,
+        Term::PieceSquares,
+        Term::Mobility,
+        Term::PawnStructure,
+        Term::PassedPawns,
+        Term::KingSafety,
+        Term::BishopPair,
+    ];
+
+    /// The terms [Board] doesn't keep incrementally updated totals of
+    pub const POSITIONAL: [Term; 5] = [
+        Term::Mobility,
+        Term::PawnStructure,
+        Term::PassedPawns,
+        Term::KingSafety,
+        Term::BishopPair,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Term::Material => "Material",
+            Term::PieceSquares => "Piece squares",
+            Term::Mobility => "Mobility",
+            Term::PawnStructure => "Pawn structure",
+            Term::PassedPawns => "Passed pawns",
+            Term::KingSafety => "King safety",
+            Term::BishopPair => "Bishop pair",
+        }
+    }
+}
+
+/// Every evaluation term of a position for both colors, printed as a table by [Display]
+#[derive(Clone, Debug)]
+pub struct Trace {
+    /// Indexed by `[term][color]`, in the order of [Term::ALL]
+    pub terms: [[Score; 2]; Term::ALL.len()],
+    pub phase: i32,
+    pub side_to_move: Color,
+}
+
+impl Trace {
+    /// Returns the sum of every term for `color`
+    pub fn total(&self, color: Color) -> Score {
+        self.terms.iter().map(|scores| scores[color as usize]).sum()
+    }
+
+    /// Returns the tapered evaluation in centipawns from white's point of view
+    pub fn score(&self) -> i32 {
+        (self.total(Color::White) - self.total(Color::Black)).taper(self.phase, MAX_PHASE)
+    }
+}
+
+/// Evaluates `board` term by term with the default weights
+pub fn trace(board: &Board) -> Trace {
+    trace_with(board, &EvalParams::DEFAULT)
+}
+
+/// Evaluates `board` term by term with the weights `params`
+pub fn trace_with(board: &Board, params: &EvalParams) -> Trace {
+    let pawns = PawnEntry::compute(board, params);
+    let mut terms = [[Score::ZERO; 2]; Term::ALL.len()];
+    for (term, scores) in Term::ALL.into_iter().zip(&mut terms) {
+        for color in Color::ALL {
+            scores[color as usize] = evaluate_term(board, params, &pawns, color, term);
+        }
+    }
+    Trace {
+        terms,
+        phase: phase(board),
+        side_to_move: board.side_to_move(),
+    }
+}
+
+fn write_row(f: &mut fmt::Formatter, name: &str, white: Score, black: Score) -> fmt::Result {
+    let total = white - black;
+    writeln!(
+        f,
+        "{name:>15} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6}",
+        white.mg, white.eg, black.mg, black.eg, total.mg, total.eg
+    )
+}
+
+const SEPARATOR: &str = "----------------+---------------+---------------+--------------";
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>15} | {:^13} | {:^13} | {:^13}",
+            "Term", "White", "Black", "Total"
+        )?;
+        writeln!(
+            f,
+            "{:>15} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6}",
+            "", "MG", "EG", "MG", "EG", "MG", "EG"
+        )?;
+        writeln!(f, "{SEPARATOR}")?;
+        for (term, scores) in Term::ALL.into_iter().zip(&self.terms) {
+            write_row(f, term.name(), scores[0], scores[1])?;
+        }
+        writeln!(f, "{SEPARATOR}")?;
+        write_row(
+            f,
+            "Total",
+            self.total(Color::White),
+            self.total(Color::Black),
+        )?;
+        writeln!(f)?;
+        writeln!(f, "Phase: {}/{MAX_PHASE}", self.phase)?;
+        let score = self.score();
+        writeln!(f, "Final: {score} cp (white's point of view)")?;
+        let relative = match self.side_to_move {
+            Color::White => score,
+            Color::Black => -score,
+        };
+        writeln!(f, "Final: {relative} cp ({} to move)", self.side_to_move)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{evaluate, pawns::PawnTable};
+
+    #[test]
+    fn trace_matches_evaluation() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "2r3k1/5ppp/p3p3/1p1pP3/3P2Q1/P1q3P1/5P1P/2R3K1 b - - 0 30",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            let trace = trace(&board);
+            let relative = match board.side_to_move() {
+                Color::White => trace.score(),
+                Color::Black => -trace.score(),
+            };
+            assert_eq!(relative, evaluate(&board, &mut PawnTable::new()), "{fen}");
+            assert_eq!(trace.to_string().lines().count(), Term::ALL.len() + 9);
+        }
+    }
+}
@@ -2,16 +2,53 @@
 #![allow(unused)]
 
 use board::Board;
+use engine::{Engine, START_FEN};
 
 mod board;
+mod engine;
+mod eval;
 mod moves;
+mod search;
+mod uci;
+mod xboard;
 
 fn main() {
-    let b = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 0").unwrap();
-    print!("{b}");
-    println!("Move Generation:");
-    let moves = b.gen_moves();
-    for m in moves {
-        println!("{m}");
+    // `chessbot eval trace [fen]` prints the evaluation breakdown of a position
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() >= 2 && args[0] == "eval" && args[1] == "trace" {
+        let fen = if args.len() > 2 {
+            args[2..].join(" ")
+        } else {
+            START_FEN.to_string()
+        };
+        match Board::from_fen(&fen) {
+            Some(board) => print!("{}", eval::trace::trace(&board)),
+            None => eprintln!("invalid fen: {fen}"),
+        }
+        return;
     }
+
+    // the first command decides which protocol to speak
+    let mut engine = Engine::new();
+    let mut lines = stdin_lines();
+    let Some(first) = lines.next() else {
+        return;
+    };
+    if first.trim() == "xboard" {
+        xboard::run(engine, lines);
+    } else {
+        uci::run(&mut engine, std::iter::once(first).chain(lines));
+    }
+}
+
+/// Returns the lines of standard input, which unlike [std::io::Stdin::lines] can be sent to
+/// another thread
+fn stdin_lines() -> impl Iterator<Item = String> + Send {
+    std::iter::from_fn(|| {
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end().to_string()),
+        }
+    })
 }
@@ -1,6 +1,6 @@
-use crate::board::bitboard::Square;
+use crate::board::{bitboard::Square, Color, Piece};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Promotion {
     Queen,
     Rook,
@@ -8,7 +8,26 @@ pub enum Promotion {
     Knight,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Promotion {
+    pub const ALL: [Promotion; 4] = [
+        Promotion::Queen,
+        Promotion::Rook,
+        Promotion::Bishop,
+        Promotion::Knight,
+    ];
+
+    /// Returns the piece the pawn becomes
+    pub fn piece(self) -> Piece {
+        match self {
+            Promotion::Queen => Piece::Queen,
+            Promotion::Rook => Piece::Rook,
+            Promotion::Bishop => Piece::Bishop,
+            Promotion::Knight => Piece::Knight,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Move {
     pub to: Square,
     pub from: Square,
@@ -24,8 +43,18 @@ impl Move {
         }
     }
 
+    /// Returns the move in long algebraic notation, e.g. `e2e4` or `e7e8q`
     pub fn lan_str(&self) -> String {
-        format!("{}{}", self.from, self.to)
+        match self.promotion {
+            // promotions are always written in lowercase
+            Some(promotion) => format!(
+                "{}{}{}",
+                self.from,
+                self.to,
+                promotion.piece().to_char(Color::Black)
+            ),
+            None => format!("{}{}", self.from, self.to),
+        }
     }
 }
 
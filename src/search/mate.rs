@@ -0,0 +1,252 @@
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{board::Board, moves::Move};
+
+/// How often, in nodes, the stop flag is checked
+const CHECK_INTERVAL: u64 = 4096;
+
+/// The shortest forced mate found by a [MateSolver]
+#[derive(Clone, Debug)]
+pub struct MateSolution {
+    /// Number of the attacker's moves to mate
+    pub moves: u8,
+    /// The key move followed by the longest defence and the fastest mate against it
+    pub pv: Vec<Move>,
+}
+
+/// The outcome of [MateSolver::solve]
+#[derive(Clone, Debug, Default)]
+pub struct MateReport {
+    /// `None` if there is no mate within the requested number of moves, or the solver was
+    /// stopped before finding one
+    ///
+    /// Shorter mates are looked for first, so a mate found before a stop is still the shortest.
+    pub solution: Option<MateSolution>,
+    /// Other first moves that mate within the requested number of moves, with the number of
+    /// moves each needs
+    pub cooks: Vec<(Move, u8)>,
+    /// Whether the solver was stopped before it could finish, then the cooks may be incomplete
+    /// and a missing solution proves nothing
+    pub stopped: bool,
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+/// Proves or refutes mate in a fixed number of moves for the side to move, as needed to
+/// check composed problems
+///
+/// Unlike the regular search this looks at every move of the attacker, since the key of a
+/// problem is often a quiet move, except for the final one which has to give check. The
+/// fifty-move rule and repetitions are ignored, as is usual for compositions.
+pub struct MateSolver {
+    board: Board,
+    stop: Arc<AtomicBool>,
+    nodes: u64,
+    stopped: bool,
+    /// Whether the attacker to move mates within a number of moves, by position and moves
+    proven: HashMap<(u64, u8), bool>,
+}
+
+impl MateSolver {
+    pub fn new(board: Board) -> Self {
+        Self {
+            board,
+            stop: Arc::new(AtomicBool::new(false)),
+            nodes: 0,
+            stopped: false,
+            proven: HashMap::new(),
+        }
+    }
+
+    /// Stops solving once `stop` is set, e.g. by another thread
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Looks for the shortest mate of the side to move within `max_moves` moves, along with
+    /// every other first move that mates within `max_moves`
+    pub fn solve(&mut self, max_moves: u8) -> MateReport {
+        let start = Instant::now();
+        self.nodes = 0;
+        self.stopped = false;
+
+        // the fastest mate of each first move, searching shorter mates first
+        let mut mates = Vec::new();
+        let mut unsolved = self.board.gen_moves();
+        for moves in 1..=max_moves {
+            let mut index = 0;
+            while index < unsolved.len() && !self.stopped {
+                let mv = unsolved[index];
+                if self.mates_after(mv, moves) {
+                    mates.push((unsolved.remove(index), moves));
+                } else {
+                    index += 1;
+                }
+            }
+        }
+
+        // the line of a proven mate mostly comes from the proven table, so it is finished
+        // even after a stop
+        let stopped = mem::replace(&mut self.stopped, false);
+        let stop = mem::take(&mut self.stop);
+        let solution = mates.first().map(|&(key, moves)| MateSolution {
+            moves,
+            pv: self.main_line(key, moves),
+        });
+        self.stop = stop;
+        MateReport {
+            solution,
+            cooks: mates.into_iter().skip(1).collect(),
+            stopped,
+            nodes: self.nodes,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// Returns true if the attacker to move can mate within `moves` moves
+    fn mates_within(&mut self, moves: u8) -> bool {
+        let key = (self.board.hash(), moves);
+        if let Some(&mates) = self.proven.get(&key) {
+            return mates;
+        }
+        let mut mates = false;
+        for mv in self.board.gen_moves() {
+            if self.mates_after(mv, moves) {
+                mates = true;
+                break;
+            }
+            if self.stopped {
+                return false;
+            }
+        }
+        self.proven.insert(key, mates);
+        mates
+    }
+
+    /// Returns true if playing `mv` mates within `moves` moves, `mv` included
+    fn mates_after(&mut self, mv: Move, moves: u8) -> bool {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) && self.stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+        if self.stopped {
+            return false;
+        }
+
+        let undo = self.board.make_move(mv);
+        let replies = self.board.gen_moves();
+        let mates = if replies.is_empty() {
+            // stalemate doesn't count
+            self.board.in_check()
+        } else if moves == 1 {
+            false
+        } else {
+            replies.into_iter().all(|reply| {
+                let undo = self.board.make_move(reply);
+                let mates = self.mates_within(moves - 1);
+                self.board.unmake_move(reply, undo);
+                mates
+            })
+        };
+        self.board.unmake_move(mv, undo);
+        mates
+    }
+
+    /// Returns the defender's reply that puts off the mate the longest, with the number of
+    /// moves the attacker still needs after it
+    fn longest_defence(&mut self, moves: u8) -> Option<(Move, u8)> {
+        let mut longest = None;
+        for reply in self.board.gen_moves() {
+            let undo = self.board.make_move(reply);
+            let needed = (1..=moves).find(|&needed| self.mates_within(needed));
+            self.board.unmake_move(reply, undo);
+            if let Some(needed) = needed
+                && longest.is_none_or(|(_, most)| needed > most)
+            {
+                longest = Some((reply, needed));
+            }
+        }
+        longest
+    }
+
+    /// Returns the line starting with `key`, which mates in `moves` moves, where the defender
+    /// holds out the longest and the attacker mates the fastest
+    fn main_line(&mut self, key: Move, moves: u8) -> Vec<Move> {
+        let mut line = vec![key];
+        let mut undos = vec![self.board.make_move(key)];
+        let mut left = moves - 1;
+        while left > 0 {
+            let Some((reply, needed)) = self.longest_defence(left) else {
+                break;
+            };
+            line.push(reply);
+            undos.push(self.board.make_move(reply));
+            let Some(attack) = self
+                .board
+                .gen_moves()
+                .into_iter()
+                .find(|&mv| self.mates_after(mv, needed))
+            else {
+                break;
+            };
+            line.push(attack);
+            undos.push(self.board.make_move(attack));
+            left = needed - 1;
+        }
+        for (&mv, undo) in line.iter().zip(undos).rev() {
+            self.board.unmake_move(mv, undo);
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(fen: &str, moves: u8) -> MateReport {
+        MateSolver::new(Board::from_fen(fen).unwrap()).solve(moves)
+    }
+
+    #[test]
+    fn finds_the_shortest_mate() {
+        // back rank mate in one, a slower mate with the other rook is a cook
+        let report = solve("6k1/5ppp/8/8/8/8/8/RR4K1 w - - 0 1", 2);
+        let solution = report.solution.unwrap();
+        assert_eq!(solution.moves, 1);
+        assert_eq!(solution.pv.len(), 1);
+        assert!(["a1a8", "b1b8"].contains(&solution.pv[0].lan_str().as_str()));
+        assert!(report.cooks.iter().any(|&(_, moves)| moves == 1));
+
+        // a quiet key: after Kb6 black's king has to move and Rd8 mates
+        let report = solve("k7/8/2K5/8/8/8/8/3R4 w - - 0 1", 2);
+        let solution = report.solution.unwrap();
+        assert_eq!(solution.moves, 2);
+        assert_eq!(solution.pv.len(), 3);
+
+        // a stop while looking for cooks keeps the mate, the first check comes after it is found
+        let board = Board::from_fen("k7/8/2K5/8/8/8/8/3R4 w - - 0 1").unwrap();
+        let report = MateSolver::new(board)
+            .with_stop(Arc::new(AtomicBool::new(true)))
+            .solve(6);
+        assert!(report.stopped);
+        let solution = report.solution.unwrap();
+        assert_eq!(solution.moves, 2);
+        assert_eq!(solution.pv.len(), 3);
+
+        // lone king and knight can't mate
+        let report = solve("k7/8/2K5/8/8/8/8/3N4 w - - 0 1", 3);
+        assert!(report.solution.is_none());
+        assert!(report.cooks.is_empty());
+        assert!(!report.stopped);
+    }
+}
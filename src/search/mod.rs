@@ -0,0 +1,1320 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use movepick::{MovePicker, OrderingTables};
+use params::{Reductions, SearchParams};
+use time::TimeManager;
+use tt::{Bound, TranspositionTable, TtEntry};
+
+use crate::{
+    board::{makemove::Undo, Board, Piece},
+    eval::{evaluate, pawns::PawnTable, PIECE_VALUES},
+    moves::Move,
+};
+
+pub mod mate;
+mod movepick;
+pub mod params;
+pub mod time;
+pub mod tt;
+
+/// Score for being checkmated at the root, a mate `n` plies away scores `MATE - n`
+pub const MATE: i32 = 30_000;
+/// Bound above any reachable score
+pub const INFINITY: i32 = 31_000;
+/// Deepest ply the search will ever reach
+pub const MAX_PLY: usize = 128;
+/// Most threads a search can run on, see [Searcher::with_threads]
+pub const MAX_THREADS: usize = 256;
+
+/// How often, in nodes, the clock is checked
+const CHECK_INTERVAL: u64 = 2048;
+/// First depth searched with an aspiration window, earlier scores are too unsettled
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+/// Initial distance of the aspiration window's bounds from the last iteration's score
+const ASPIRATION_WINDOW: i32 = 25;
+/// Safety margin of delta pruning, a capture is skipped if even winning the captured
+/// piece plus this much can't raise alpha
+const DELTA_MARGIN: i32 = 200;
+
+/// Returns true if `score` is a forced mate for either side
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE - MAX_PLY as i32
+}
+
+/// Conditions for stopping a search, the first one reached wins, and the root moves it may
+/// choose from
+///
+/// With no limits set the search runs until [MAX_PLY].
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    /// Deepest iteration to search, in plies
+    pub depth: Option<u8>,
+    /// Number of nodes after which the search stops
+    pub nodes: Option<u64>,
+    /// Clock budget of the move, see [TimeManager]
+    pub time: Option<TimeManager>,
+    /// Root moves to choose from, every legal move if empty
+    pub search_moves: Vec<Move>,
+    /// Root moves never to choose, applied after [search_moves][Self::search_moves]
+    pub exclude_moves: Vec<Move>,
+}
+
+impl SearchLimits {
+    /// Returns true if the search may play `mv` at the root
+    pub fn allows(&self, mv: Move) -> bool {
+        (self.search_moves.is_empty() || self.search_moves.contains(&mv))
+            && !self.exclude_moves.contains(&mv)
+    }
+}
+
+/// The result of a completed iteration of the search
+#[derive(Clone, Debug, Default)]
+pub struct SearchReport {
+    pub depth: u8,
+    /// Deepest ply reached, including quiescence search
+    pub seldepth: usize,
+    /// Rank of this line among the best root moves, starting at 1
+    pub multipv: usize,
+    /// Score in centipawns from the side to move's point of view
+    pub score: i32,
+    /// Whether `score` is exact or only a bound, reported while the aspiration window is
+    /// widened after the score fell outside of it
+    pub bound: Bound,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    /// Principal variation, the expected line of play starting with the best move
+    pub pv: Vec<Move>,
+    /// Transposition table usage in permille
+    pub hashfull: u32,
+}
+
+impl SearchReport {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+
+    /// Returns the expected reply to the best move, worth thinking about on the opponent's time
+    pub fn ponder_move(&self) -> Option<Move> {
+        self.pv.get(1).copied()
+    }
+}
+
+/// Iterative deepening negamax alpha-beta search over a single position
+pub struct Searcher {
+    board: Board,
+    limits: SearchLimits,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+    /// Set once the root has a best move, the search can't be stopped before that since it
+    /// would have nothing to play
+    can_stop: bool,
+    /// Score of the best root move of the running iteration so far
+    root_score: i32,
+    /// Triangular principal variation table, `pv[ply]` is the best line found from `ply` onwards
+    pv: Vec<Vec<Move>>,
+    /// The move played to reach each ply
+    played: [Option<Move>; MAX_PLY + 1],
+    tables: OrderingTables,
+    tt: Arc<TranspositionTable>,
+    pawn_table: PawnTable,
+    /// Tables of earlier searches for the helper threads, and theirs once they are done
+    helper_tables: Vec<ThreadTables>,
+    /// Set from outside the search to make it stop as soon as possible
+    stop: Arc<AtomicBool>,
+    /// Set while searching on the opponent's time, the clock is ignored until it is cleared
+    ponder: Arc<AtomicBool>,
+    /// Whether the running search started out pondering
+    pondered: bool,
+    /// Number of threads searching together, including this one
+    threads: usize,
+    /// 0 for the main thread, which reports and decides, and counting up for its helpers
+    thread_id: usize,
+    /// Nodes searched by all threads together, each adds its own every [CHECK_INTERVAL] nodes
+    shared_nodes: Arc<AtomicU64>,
+    seldepth: usize,
+    /// Number of legal moves at the root, known after the first iteration
+    root_moves: usize,
+    /// Nodes spent below the current best root move in the running iteration
+    best_move_nodes: u64,
+    /// Number of best root moves to find a line for, see [Searcher::with_multi_pv]
+    multi_pv: usize,
+    /// Root moves skipped by the running search, those leading the better lines
+    excluded: Vec<Move>,
+    params: SearchParams,
+    reductions: Reductions,
+    /// Move left out at each ply while checking whether it is singular
+    skipped: [Option<Move>; MAX_PLY + 1],
+    /// Zobrist keys of the game positions before the root followed by those on the current
+    /// search path, the last one being the parent of the current node
+    keys: Vec<u64>,
+}
+
+/// The tables each search thread fills for itself, kept between searches so that move
+/// ordering statistics and cached pawn evaluations carry over to the next move
+pub struct ThreadTables {
+    ordering: OrderingTables,
+    pawns: PawnTable,
+}
+
+impl ThreadTables {
+    pub fn new() -> Self {
+        Self {
+            ordering: OrderingTables::new(),
+            pawns: PawnTable::new(),
+        }
+    }
+}
+
+impl Default for ThreadTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Searcher {
+    pub fn new(board: Board, limits: SearchLimits) -> Self {
+        Self::with_thread_tables(board, limits, Vec::new())
+    }
+
+    /// Creates a searcher that reuses `tables` of earlier searches, one for each thread, see
+    /// [Searcher::into_thread_tables]
+    ///
+    /// Threads without tables get new ones.
+    pub fn with_thread_tables(
+        board: Board,
+        limits: SearchLimits,
+        mut tables: Vec<ThreadTables>,
+    ) -> Self {
+        let own = tables.pop().unwrap_or_default();
+        Self {
+            board,
+            limits,
+            start: Instant::now(),
+            nodes: 0,
+            stopped: false,
+            can_stop: false,
+            root_score: -INFINITY,
+            pv: vec![Vec::new(); MAX_PLY + 1],
+            played: [None; MAX_PLY + 1],
+            tables: own.ordering,
+            tt: Arc::new(TranspositionTable::default()),
+            pawn_table: own.pawns,
+            helper_tables: tables,
+            stop: Arc::new(AtomicBool::new(false)),
+            ponder: Arc::new(AtomicBool::new(false)),
+            pondered: false,
+            threads: 1,
+            thread_id: 0,
+            shared_nodes: Arc::new(AtomicU64::new(0)),
+            seldepth: 0,
+            root_moves: 0,
+            best_move_nodes: 0,
+            multi_pv: 1,
+            excluded: Vec::new(),
+            params: SearchParams::DEFAULT,
+            reductions: SearchParams::DEFAULT.reductions(),
+            skipped: [None; MAX_PLY + 1],
+            keys: Vec::new(),
+        }
+    }
+
+    /// Uses `tt` instead of a private table, so results carry over between searches
+    pub fn with_table(mut self, tt: Arc<TranspositionTable>) -> Self {
+        self.tt = tt;
+        self
+    }
+
+    /// Stops the search once `stop` is set, e.g. by another thread
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Ignores [SearchLimits::time] for as long as `ponder` is set
+    ///
+    /// Clearing it on a ponder hit turns the search into a normal timed one. The clock keeps
+    /// counting from the start of the search, so the time spent pondering is never billed twice.
+    pub fn with_ponder(mut self, ponder: Arc<AtomicBool>) -> Self {
+        self.ponder = ponder;
+        self
+    }
+
+    /// Sets the Zobrist keys of the positions played before the root, oldest first, so that
+    /// repetitions of them are scored as draws
+    pub fn with_history(mut self, keys: Vec<u64>) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Searches a separate line for each of the `lines` best root moves, reported in order
+    /// with their [SearchReport::multipv] rank
+    ///
+    /// The returned result is always the best line.
+    pub fn with_multi_pv(mut self, lines: usize) -> Self {
+        self.multi_pv = lines.max(1);
+        self
+    }
+
+    /// Uses `params` instead of [SearchParams::DEFAULT] to decide what to prune, reduce and extend
+    pub fn with_params(mut self, params: SearchParams) -> Self {
+        self.reductions = params.reductions();
+        self.params = params;
+        self
+    }
+
+    /// Searches on `threads` threads sharing the transposition table
+    ///
+    /// Helper threads search the same position with their own move ordering tables, starting
+    /// at alternating depths so that they spread out over the tree and fill the table with
+    /// results the main thread can use. Only the main thread reports and picks the move.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.clamp(1, MAX_THREADS);
+        self
+    }
+
+    /// Searches with increasing depth until a limit is reached, calling `report` after
+    /// every completed iteration, and returns the last completed iteration
+    ///
+    /// If [SearchLimits] leave none of the legal root moves there is nothing to search, the
+    /// result has no move and no score rather than the mate or stalemate of a moveless root.
+    pub fn run(&mut self, report: impl FnMut(&SearchReport)) -> SearchReport {
+        let legal = self.board.gen_moves();
+        if !legal.is_empty() && !legal.into_iter().any(|mv| self.limits.allows(mv)) {
+            return SearchReport::default();
+        }
+        self.tt.new_search();
+        self.shared_nodes = Arc::new(AtomicU64::new(0));
+        let helpers_stop = Arc::new(AtomicBool::new(false));
+        let helpers: Vec<Searcher> = (1..self.threads)
+            .map(|id| self.helper(id, helpers_stop.clone()))
+            .collect();
+
+        let mut best = thread::scope(|scope| {
+            let running: Vec<_> = helpers
+                .into_iter()
+                .map(|mut helper| {
+                    scope.spawn(move || {
+                        helper.iterate(|_| {});
+                        helper.into_thread_tables()
+                    })
+                })
+                .collect();
+            let best = self.iterate(report);
+            helpers_stop.store(true, Ordering::Relaxed);
+            for helper in running {
+                let tables = helper.join().expect("helper thread panicked");
+                self.helper_tables.extend(tables);
+            }
+            best
+        });
+        best.nodes = self.shared_nodes.load(Ordering::Relaxed);
+        best
+    }
+
+    /// Returns the tables of every thread, to be reused by the next search with
+    /// [Searcher::with_thread_tables]
+    pub fn into_thread_tables(mut self) -> Vec<ThreadTables> {
+        self.helper_tables.push(ThreadTables {
+            ordering: self.tables,
+            pawns: self.pawn_table,
+        });
+        self.helper_tables
+    }
+
+    /// Returns a searcher for helper thread `id` that runs until `stop` is set
+    fn helper(&mut self, id: usize, stop: Arc<AtomicBool>) -> Searcher {
+        let limits = SearchLimits {
+            search_moves: self.limits.search_moves.clone(),
+            exclude_moves: self.limits.exclude_moves.clone(),
+            ..Default::default()
+        };
+        let tables = self.helper_tables.pop().into_iter().collect();
+        let mut helper = Searcher::with_thread_tables(self.board.clone(), limits, tables)
+            .with_table(self.tt.clone())
+            .with_stop(stop)
+            .with_history(self.keys.clone())
+            .with_params(self.params.clone());
+        helper.thread_id = id;
+        helper.shared_nodes = self.shared_nodes.clone();
+        helper
+    }
+
+    /// Returns the number of nodes searched by all threads so far
+    fn total_nodes(&self) -> u64 {
+        self.shared_nodes.load(Ordering::Relaxed) + self.nodes % CHECK_INTERVAL
+    }
+
+    /// The iterative deepening loop of a single thread
+    fn iterate(&mut self, mut report: impl FnMut(&SearchReport)) -> SearchReport {
+        self.start = Instant::now();
+        self.nodes = 0;
+        self.stopped = false;
+        self.can_stop = false;
+        self.seldepth = 0;
+        self.pondered = self.is_pondering();
+
+        // odd helpers stay a ply ahead of the others
+        let first_depth = 1 + (self.thread_id % 2) as u8;
+        let max_depth = self
+            .limits
+            .depth
+            .unwrap_or(MAX_PLY as u8)
+            .min(MAX_PLY as u8);
+        let mut best = SearchReport::default();
+        let mut lines: Vec<SearchReport> = Vec::new();
+        for depth in first_depth..=max_depth {
+            let iteration_start = self.nodes;
+            let mut effort = 0.0;
+            let mut completed: Vec<SearchReport> = Vec::new();
+            for index in 0..self.multi_pv {
+                // every line searches the root moves not already leading a better line
+                self.excluded.clear();
+                self.excluded
+                    .extend(completed.iter().filter_map(SearchReport::best_move));
+                self.best_move_nodes = 0;
+                let score = self.aspiration(depth, lines.get(index), index + 1, &mut report);
+                if self.stopped || index > 0 && self.pv[0].is_empty() {
+                    break;
+                }
+                if index == 0 {
+                    effort =
+                        self.best_move_nodes as f64 / (self.nodes - iteration_start).max(1) as f64;
+                }
+                completed.push(self.line_report(depth, index + 1, score, Bound::Exact));
+            }
+            // an iteration counts once its first line is complete, only a first iteration
+            // stopped early falls back to the best root move it got to
+            if completed.is_empty() {
+                if best.pv.is_empty() && !self.pv[0].is_empty() {
+                    best = self.line_report(depth, 1, self.root_score, Bound::Lower);
+                    report(&best);
+                }
+                break;
+            }
+            // pruning can make a later line come out better than an earlier one
+            completed.sort_by_key(|line| -line.score);
+            for (index, line) in completed.iter_mut().enumerate() {
+                line.multipv = index + 1;
+                report(line);
+            }
+            best = completed[0].clone();
+            let score = best.score;
+            lines = completed;
+            if self.stopped {
+                break;
+            }
+
+            // no need to look deeper once the root has no moves or a mate is found
+            if best.pv.is_empty() || is_mate_score(score) && MATE - score.abs() <= depth as i32 {
+                break;
+            }
+            let pondering = self.is_pondering();
+            if let Some(time) = &mut self.limits.time {
+                // with a single legal move any time spent thinking is wasted
+                if self.root_moves == 1 && !pondering {
+                    break;
+                }
+                if time.iteration_done(best.pv[0], score, effort) && !pondering {
+                    break;
+                }
+            }
+        }
+        // table cutoffs can cut the line short, but the reply is still worth pondering on
+        if let [best_move] = best.pv[..]
+            && let Some(reply) = self.reply_from_table(best_move)
+        {
+            best.pv.push(reply);
+        }
+        self.shared_nodes
+            .fetch_add(self.nodes % CHECK_INTERVAL, Ordering::Relaxed);
+        best.nodes = self.nodes;
+        best.elapsed = self.start.elapsed();
+        best.hashfull = self.tt.hashfull();
+        best
+    }
+
+    /// Searches the root in a window around `previous`'s score, widening it on whichever side
+    /// the score falls outside until it lands inside, and returns the score
+    ///
+    /// Every failed attempt is reported with its bound as line `multipv`.
+    fn aspiration(
+        &mut self,
+        depth: u8,
+        previous: Option<&SearchReport>,
+        multipv: usize,
+        report: &mut impl FnMut(&SearchReport),
+    ) -> i32 {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match previous {
+            Some(line) if depth >= ASPIRATION_MIN_DEPTH && !is_mate_score(line.score) => {
+                (line.score - delta, line.score + delta)
+            }
+            _ => (-INFINITY, INFINITY),
+        };
+        loop {
+            // the last iteration's line is the fallback move ordering at the root
+            self.pv[0] = previous.map_or(Vec::new(), |line| line.pv.clone());
+            let score = self.negamax(depth, alpha, beta, 0);
+            if self.stopped || alpha < score && score < beta {
+                return score;
+            }
+            let bound = if score <= alpha {
+                // keep beta close, the score will likely not go back up by much
+                beta = (alpha + beta) / 2;
+                alpha = (score - delta).max(-INFINITY);
+                Bound::Upper
+            } else {
+                beta = (score + delta).min(INFINITY);
+                Bound::Lower
+            };
+            delta += delta / 2;
+
+            let mut line = self.line_report(depth, multipv, score, bound);
+            // failing low leaves no line behind, the old one is still the best guess
+            if line.pv.is_empty()
+                && let Some(previous) = previous
+            {
+                line.pv = previous.pv.clone();
+            }
+            report(&line);
+        }
+    }
+
+    /// Returns the report of root line `multipv` at `depth` with the current principal variation
+    fn line_report(&self, depth: u8, multipv: usize, score: i32, bound: Bound) -> SearchReport {
+        SearchReport {
+            depth,
+            seldepth: self.seldepth,
+            multipv,
+            score,
+            bound,
+            nodes: self.total_nodes(),
+            elapsed: self.start.elapsed(),
+            pv: self.pv[0].clone(),
+            hashfull: self.tt.hashfull(),
+        }
+    }
+
+    fn negamax(&mut self, mut depth: u8, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            self.stopped = true;
+            return 0;
+        }
+        // the root falls back to the last iteration's best move in case its entry was overwritten
+        let root_move = if ply == 0 {
+            self.pv[0].first().copied()
+        } else {
+            None
+        };
+        self.pv[ply].clear();
+
+        self.seldepth = self.seldepth.max(ply);
+        if ply > 0 && (self.board.half_moves() >= 100 || self.is_repetition()) {
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return evaluate(&self.board, &mut self.pawn_table);
+        }
+        let in_check = self.board.in_check();
+        if in_check && self.params.check_extension {
+            depth += 1;
+        }
+        if depth == 0 {
+            return self.quiesce(alpha, beta, ply);
+        }
+
+        let hash = self.board.hash();
+        // a search without the singular candidate is a different search of the same position
+        let skipped = self.skipped[ply];
+        let entry = if skipped.is_none() {
+            self.tt.probe(hash, ply)
+        } else {
+            None
+        };
+        if ply > 0
+            && let Some(entry) = entry
+            && entry.depth >= depth
+            && match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            }
+        {
+            return entry.score;
+        }
+
+        let is_pv = beta - alpha > 1;
+        let static_eval = if in_check {
+            -INFINITY
+        } else {
+            evaluate(&self.board, &mut self.pawn_table)
+        };
+        if !is_pv && !in_check && ply > 0 && skipped.is_none() {
+            if let Some(score) = self.prune_node(depth, alpha, beta, ply, static_eval, hash) {
+                return score;
+            }
+            if self.stopped {
+                return 0;
+            }
+        }
+
+        let singular = self.singular_move(depth, ply, entry);
+        if self.stopped {
+            return 0;
+        }
+
+        let hash_move = entry.and_then(|entry| entry.best_move).or(root_move);
+        let previous = ply.checked_sub(1).and_then(|prev| self.played[prev]);
+        let mut picker = MovePicker::new(
+            hash_move,
+            self.tables.killers(ply),
+            self.tables.counter_move(&self.board, previous),
+        );
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut legal_moves = 0;
+        // moves searched rather than pruned, the first one gets the full window
+        let mut searched = 0;
+        let mut quiets_tried = Vec::new();
+        while let Some(mv) = picker.next(&self.board, &self.tables) {
+            if ply == 0 && (self.excluded.contains(&mv) || !self.limits.allows(mv))
+                || skipped == Some(mv)
+            {
+                continue;
+            }
+            let is_quiet = !self.board.is_capture(mv) && mv.promotion.is_none();
+            let Some(undo) = self.make_legal_move(mv) else {
+                continue;
+            };
+            legal_moves += 1;
+            let gives_check = self.board.in_check();
+
+            // once a move saved the node from getting mated, quiet moves that can't raise
+            // alpha are skipped, -INFINITY counts as a mate score here
+            if !is_pv
+                && !in_check
+                && is_quiet
+                && !gives_check
+                && !is_mate_score(best_score)
+                && self.is_futile(depth, alpha, static_eval, quiets_tried.len())
+            {
+                self.board.unmake_move(mv, undo);
+                continue;
+            }
+
+            if is_quiet {
+                quiets_tried.push(mv);
+            }
+            self.played[ply] = Some(mv);
+            self.keys.push(hash);
+            let nodes_before = self.nodes;
+            let new_depth = depth - 1 + u8::from(singular == Some(mv));
+
+            let score = if searched == 0 {
+                -self.negamax(new_depth, -beta, -alpha, ply + 1)
+            } else {
+                // later moves only have to prove they are no better than the best one so far,
+                // which a null window does cheaply, late quiet ones even with less depth
+                let mut reduced = new_depth;
+                if self.params.late_move_reductions
+                    && depth >= self.params.lmr_min_depth
+                    && is_quiet
+                    && !in_check
+                    && !gives_check
+                {
+                    let mut reduction = self.reductions.get(depth, legal_moves);
+                    if is_pv {
+                        reduction = reduction.saturating_sub(1);
+                    }
+                    reduced = new_depth.saturating_sub(reduction).max(1);
+                }
+                let mut score = -self.negamax(reduced, -alpha - 1, -alpha, ply + 1);
+                if score > alpha && reduced < new_depth && !self.stopped {
+                    score = -self.negamax(new_depth, -alpha - 1, -alpha, ply + 1);
+                }
+                if score > alpha && score < beta && !self.stopped {
+                    score = -self.negamax(new_depth, -beta, -alpha, ply + 1);
+                }
+                score
+            };
+            searched += 1;
+            self.keys.pop();
+            self.board.unmake_move(mv, undo);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    best_move = Some(mv);
+                    self.update_pv(ply, mv);
+                    if ply == 0 {
+                        self.best_move_nodes = self.nodes - nodes_before;
+                        self.root_score = score;
+                        self.can_stop = true;
+                    }
+                }
+                if score >= beta {
+                    if is_quiet {
+                        self.tables.update_quiet(
+                            &self.board,
+                            ply,
+                            depth,
+                            mv,
+                            previous,
+                            &quiets_tried,
+                        );
+                    }
+                    break;
+                }
+            }
+        }
+
+        if ply == 0 && self.excluded.is_empty() {
+            self.root_moves = legal_moves;
+        }
+        if legal_moves == 0 {
+            return if skipped.is_some() {
+                // the singular candidate was the only move
+                alpha
+            } else if in_check {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        // with moves left out the result isn't the position's true value
+        let restricted = ply == 0
+            && !(self.excluded.is_empty()
+                && self.limits.search_moves.is_empty()
+                && self.limits.exclude_moves.is_empty());
+        if !restricted && skipped.is_none() {
+            self.tt.store(
+                hash,
+                ply,
+                TtEntry {
+                    best_move,
+                    score: best_score,
+                    depth,
+                    bound,
+                },
+            );
+        }
+        best_score
+    }
+
+    /// Tries to cut off a non-PV node before searching any move, returns its score if that
+    /// works
+    ///
+    /// The side to move is not in check and `static_eval` is its evaluation of the position.
+    fn prune_node(
+        &mut self,
+        depth: u8,
+        alpha: i32,
+        beta: i32,
+        ply: usize,
+        static_eval: i32,
+        hash: u64,
+    ) -> Option<i32> {
+        let params = &self.params;
+
+        // reverse futility: far enough above beta that no move will drop below it
+        if params.reverse_futility
+            && depth <= params.reverse_futility_max_depth
+            && !is_mate_score(beta)
+            && static_eval - params.reverse_futility_margin * depth as i32 >= beta
+        {
+            return Some(static_eval);
+        }
+
+        // razoring: so far below alpha that only captures could help
+        if params.razoring
+            && depth <= params.razoring_max_depth
+            && static_eval + params.razoring_margin * (depth as i32) < alpha
+        {
+            let score = self.quiesce(alpha, alpha + 1, ply);
+            if score <= alpha {
+                return Some(score);
+            }
+        }
+
+        // null move: if passing still beats beta, a real move will too, except in zugzwang,
+        // which mostly happens when only pawns are left
+        let params = &self.params;
+        if params.null_move
+            && depth >= params.null_move_min_depth
+            && static_eval >= beta
+            && !is_mate_score(beta)
+            && self.played[ply - 1].is_some()
+            && self.board.has_non_pawn_material(self.board.side_to_move())
+        {
+            let reduction = params.null_move_reduction + depth / params.null_move_depth_divisor;
+            let undo = self.board.make_null_move();
+            self.played[ply] = None;
+            self.keys.push(hash);
+            let score = -self.negamax(
+                depth.saturating_sub(1 + reduction),
+                -beta,
+                -beta + 1,
+                ply + 1,
+            );
+            self.keys.pop();
+            self.board.unmake_null_move(undo);
+            if !self.stopped && score >= beta {
+                // mates found after passing aren't proven
+                return Some(if is_mate_score(score) { beta } else { score });
+            }
+        }
+        None
+    }
+
+    /// Returns true if a quiet move that doesn't give check can be skipped, either since the
+    /// node is too far below alpha or since `quiets_tried` quiet moves already failed
+    fn is_futile(&self, depth: u8, alpha: i32, static_eval: i32, quiets_tried: usize) -> bool {
+        let params = &self.params;
+        let late = params.late_move_pruning
+            && depth <= params.late_move_pruning_max_depth
+            && quiets_tried >= params.late_move_pruning_base + depth as usize * depth as usize;
+        let futile = params.futility
+            && depth <= params.futility_max_depth
+            && static_eval + params.futility_base + params.futility_margin * depth as i32 <= alpha;
+        late || futile
+    }
+
+    /// Returns the hash move if it is singular, that is all other moves fail clearly below
+    /// its score in a reduced search, so that it deserves to be searched a ply deeper
+    fn singular_move(&mut self, depth: u8, ply: usize, entry: Option<TtEntry>) -> Option<Move> {
+        let entry = entry?;
+        let candidate = entry.best_move?;
+        if !self.params.singular_extension
+            || ply == 0
+            || depth < self.params.singular_min_depth
+            || entry.depth + 3 < depth
+            || entry.bound == Bound::Upper
+            || is_mate_score(entry.score)
+        {
+            return None;
+        }
+        let singular_beta = entry.score - self.params.singular_margin * depth as i32;
+        self.skipped[ply] = Some(candidate);
+        let score = self.negamax((depth - 1) / 2, singular_beta - 1, singular_beta, ply);
+        self.skipped[ply] = None;
+        // the verification search left its line behind
+        self.pv[ply].clear();
+        (score < singular_beta).then_some(candidate)
+    }
+
+    /// Searches captures and queen promotions until the position is quiet, so that
+    /// the static evaluation isn't taken in the middle of an exchange
+    ///
+    /// When in check every evasion is searched instead, since standing pat isn't an option.
+    fn quiesce(&mut self, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        self.pv[ply].clear();
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        if self.should_stop() {
+            self.stopped = true;
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return evaluate(&self.board, &mut self.pawn_table);
+        }
+
+        let entry = self.tt.probe(self.board.hash(), ply);
+        if let Some(entry) = entry
+            && match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            }
+        {
+            return entry.score;
+        }
+
+        let original_alpha = alpha;
+        let in_check = self.board.in_check();
+        let mut picker = if in_check {
+            MovePicker::evasions()
+        } else {
+            MovePicker::captures()
+        };
+        let stand_pat = if in_check {
+            -INFINITY
+        } else {
+            let stand_pat = evaluate(&self.board, &mut self.pawn_table);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            stand_pat
+        };
+
+        let mut best_score = stand_pat;
+        let mut best_move = None;
+        let mut legal_moves = 0;
+        while let Some(mv) = picker.next(&self.board, &self.tables) {
+            if !in_check && stand_pat + self.capture_gain(mv) + DELTA_MARGIN <= alpha {
+                continue;
+            }
+            // captures that lose material in the exchange are very unlikely to raise alpha
+            if !in_check && !self.board.see_ge(mv, 0) {
+                continue;
+            }
+            let Some(undo) = self.make_legal_move(mv) else {
+                continue;
+            };
+            legal_moves += 1;
+            let score = -self.quiesce(-beta, -alpha, ply + 1);
+            self.board.unmake_move(mv, undo);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    best_move = Some(mv);
+                    self.update_pv(ply, mv);
+                }
+                if score >= beta {
+                    break;
+                }
+            }
+        }
+
+        if in_check && legal_moves == 0 {
+            return -MATE + ply as i32;
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(
+            self.board.hash(),
+            ply,
+            TtEntry {
+                best_move,
+                score: best_score,
+                depth: 0,
+                bound,
+            },
+        );
+        best_score
+    }
+
+    /// Returns the material won by the capture or promotion `mv`, ignoring recaptures
+    fn capture_gain(&self, mv: Move) -> i32 {
+        let captured = match self.board.piece_at(mv.to) {
+            Some((piece, _)) => PIECE_VALUES[piece as usize],
+            // the only capture onto an empty square is en passant
+            None if self.board.piece_at(mv.from).map(|(piece, _)| piece) == Some(Piece::Pawn)
+                && mv.from.file() != mv.to.file() =>
+            {
+                PIECE_VALUES[Piece::Pawn as usize]
+            }
+            None => 0,
+        };
+        let promoted = mv.promotion.map_or(0, |promotion| {
+            PIECE_VALUES[promotion.piece() as usize] - PIECE_VALUES[Piece::Pawn as usize]
+        });
+        captured + promoted
+    }
+
+    /// Plays the pseudo-legal `mv` unless it would leave the own king in check
+    fn make_legal_move(&mut self, mv: Move) -> Option<Undo> {
+        let us = self.board.side_to_move();
+        let undo = self.board.make_move(mv);
+        if self
+            .board
+            .is_attacked(self.board.king_square(us), us.opposite())
+        {
+            self.board.unmake_move(mv, undo);
+            return None;
+        }
+        Some(undo)
+    }
+
+    /// Returns the transposition table's best move after `mv`, if it is legal
+    fn reply_from_table(&self, mv: Move) -> Option<Move> {
+        let mut board = self.board.clone();
+        board.make_move(mv);
+        let reply = self.tt.probe(board.hash(), 1)?.best_move?;
+        (board.is_pseudo_legal(reply) && board.is_legal(reply)).then_some(reply)
+    }
+
+    fn is_pondering(&self) -> bool {
+        self.ponder.load(Ordering::Relaxed)
+    }
+
+    /// Makes `mv` followed by the line below it the principal variation at `ply`
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let (current, below) = self.pv.split_at_mut(ply + 1);
+        let line = &mut current[ply];
+        line.clear();
+        line.push(mv);
+        line.extend_from_slice(&below[0]);
+    }
+
+    /// Returns true if the current position already occurred since the last irreversible move
+    ///
+    /// A single repetition inside the search is enough to score it as a draw, since if
+    /// repeating is good for one side it can repeat again.
+    fn is_repetition(&self) -> bool {
+        let hash = self.board.hash();
+        let reversible = (self.board.half_moves() as usize).min(self.keys.len());
+        // only positions with the same side to move can be equal
+        (2..=reversible)
+            .step_by(2)
+            .any(|back| self.keys[self.keys.len() - back] == hash)
+    }
+
+    fn should_stop(&self) -> bool {
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.shared_nodes
+                .fetch_add(CHECK_INTERVAL, Ordering::Relaxed);
+        }
+        if !self.can_stop {
+            return false;
+        }
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        // helpers have no node limit, the main thread counts their nodes against its own
+        if self
+            .limits
+            .nodes
+            .is_some_and(|limit| self.total_nodes() >= limit)
+        {
+            return true;
+        }
+        if !self.nodes.is_multiple_of(CHECK_INTERVAL) || self.is_pondering() {
+            return false;
+        }
+        // after a ponder hit the time already spent may well be past the soft limit
+        self.limits.time.as_ref().is_some_and(|time| {
+            time.hard_limit_reached() || self.pondered && time.soft_limit_reached()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::bitboard::Square;
+
+    fn search(fen: &str, limits: SearchLimits) -> (Board, SearchReport) {
+        let board = Board::from_fen(fen).unwrap();
+        let result = Searcher::new(board.clone(), limits).run(|_| {});
+        (board, result)
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let (board, result) = search(
+            "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+            SearchLimits {
+                depth: Some(3),
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.best_move(), board.parse_move("d1d8"));
+        assert_eq!(result.score, MATE - 1);
+    }
+
+    #[test]
+    fn saves_a_hanging_piece() {
+        // the pawn on d6 attacks the queen, which has to move or take it
+        let (board, result) = search(
+            "k7/8/3p4/4Q3/8/8/8/4K3 w - - 0 1",
+            SearchLimits {
+                depth: Some(4),
+                ..Default::default()
+            },
+        );
+        let best = result.best_move().unwrap();
+        assert_eq!(
+            board.piece_at(best.from).map(|(piece, _)| piece),
+            Some(Piece::Queen)
+        );
+        assert!(result.score > PIECE_VALUES[Piece::Queen as usize] / 2);
+    }
+
+    #[test]
+    fn obeys_the_limits() {
+        const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let (_, result) = search(
+            START,
+            SearchLimits {
+                depth: Some(3),
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.depth, 3);
+
+        let (_, result) = search(
+            START,
+            SearchLimits {
+                nodes: Some(5000),
+                ..Default::default()
+            },
+        );
+        assert!(result.nodes <= 5000);
+        assert!(result.best_move().is_some());
+
+        // the limit covers the nodes of every thread, give or take what the helpers haven't
+        // added to the count yet
+        let board = Board::from_fen(START).unwrap();
+        let limits = SearchLimits {
+            nodes: Some(20_000),
+            ..Default::default()
+        };
+        let result = Searcher::new(board, limits).with_threads(4).run(|_| {});
+        assert!(
+            result.nodes <= 20_000 + 3 * CHECK_INTERVAL,
+            "{}",
+            result.nodes
+        );
+
+        // a stop before the first iteration is done still leaves a move to play
+        let board = Board::from_fen(START).unwrap();
+        let result = Searcher::new(board, SearchLimits::default())
+            .with_stop(Arc::new(AtomicBool::new(true)))
+            .run(|_| {});
+        assert_eq!(result.depth, 1);
+        assert!(result.best_move().is_some());
+    }
+
+    #[test]
+    fn tactics_survive_every_switch() {
+        let switches: [fn(&mut SearchParams) -> &mut bool; 8] = [
+            |params| &mut params.null_move,
+            |params| &mut params.late_move_reductions,
+            |params| &mut params.reverse_futility,
+            |params| &mut params.futility,
+            |params| &mut params.razoring,
+            |params| &mut params.late_move_pruning,
+            |params| &mut params.check_extension,
+            |params| &mut params.singular_extension,
+        ];
+        let mut all_off = SearchParams::DEFAULT;
+        for switch in switches {
+            *switch(&mut all_off) = false;
+        }
+        let mut configs = vec![SearchParams::DEFAULT, all_off.clone()];
+        for switch in switches {
+            let mut params = SearchParams::DEFAULT;
+            *switch(&mut params) = false;
+            configs.push(params);
+            let mut params = all_off.clone();
+            *switch(&mut params) = true;
+            configs.push(params);
+        }
+
+        // Nd6+ forks king and queen, Rd8+ Rxd8 Rxd8# mates in two
+        let fork = Board::from_fen("2q1k3/8/8/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+        let mate = Board::from_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1").unwrap();
+        let limits = SearchLimits {
+            depth: Some(5),
+            ..Default::default()
+        };
+        for params in configs {
+            let result = Searcher::new(fork.clone(), limits.clone())
+                .with_params(params.clone())
+                .run(|_| {});
+            assert_eq!(result.best_move(), fork.parse_move("e4d6"), "{params:?}");
+            assert!(result.score > 0, "{params:?}");
+
+            let result = Searcher::new(mate.clone(), limits.clone())
+                .with_params(params.clone())
+                .run(|_| {});
+            // either rook can go first
+            assert_eq!(
+                result.best_move().map(|mv| mv.to),
+                Some(Square::D8),
+                "{params:?}"
+            );
+            assert_eq!(result.score, MATE - 3, "{params:?}");
+        }
+    }
+
+    #[test]
+    fn aspiration_windows() {
+        // a previous score far off on either side makes the first window fail that way
+        let board =
+            Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let limits = SearchLimits {
+            depth: Some(5),
+            ..Default::default()
+        };
+        let exact = Searcher::new(board.clone(), limits).run(|_| {});
+        for (offset, bound) in [(400, Bound::Upper), (-400, Bound::Lower)] {
+            let previous = SearchReport {
+                score: exact.score + offset,
+                pv: exact.pv.clone(),
+                ..Default::default()
+            };
+            let mut lines = Vec::new();
+            let score = Searcher::new(board.clone(), SearchLimits::default()).aspiration(
+                5,
+                Some(&previous),
+                1,
+                &mut |line: &SearchReport| lines.push(line.clone()),
+            );
+            assert!(!lines.is_empty());
+            assert!(lines.iter().all(|line| line.bound == bound));
+            // every failure moves the window further out on the same side
+            for pair in lines.windows(2) {
+                match bound {
+                    Bound::Upper => assert!(pair[1].score < pair[0].score),
+                    _ => assert!(pair[1].score > pair[0].score),
+                }
+            }
+            // the search ends inside the window, beyond the last bound
+            let last = lines.last().unwrap().score;
+            match bound {
+                Bound::Upper => assert!(score < last),
+                _ => assert!(score > last),
+            }
+        }
+
+        // in a full search the failed attempts of an iteration are reported before its
+        // final line
+        let mut lines = Vec::new();
+        let limits = SearchLimits {
+            depth: Some(7),
+            ..Default::default()
+        };
+        Searcher::new(board, limits).run(|line| lines.push(line.clone()));
+        assert!(lines.iter().any(|line| line.bound != Bound::Exact));
+        for depth in 1..=7 {
+            let iteration: Vec<_> = lines.iter().filter(|line| line.depth == depth).collect();
+            let (last, failed) = iteration.split_last().unwrap();
+            assert_eq!(last.bound, Bound::Exact);
+            assert!(failed.iter().all(|line| line.bound != Bound::Exact));
+        }
+    }
+
+    #[test]
+    fn multi_pv_lines() {
+        let board =
+            Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..Default::default()
+        };
+        let mut lines = Vec::new();
+        let result = Searcher::new(board, limits)
+            .with_multi_pv(4)
+            .run(|line| lines.push(line.clone()));
+        let last: Vec<_> = lines.iter().filter(|line| line.depth == 3).collect();
+        assert_eq!(last.len(), 4);
+        for (rank, pair) in last.windows(2).enumerate() {
+            assert_eq!(pair[0].multipv, rank + 1);
+            assert_ne!(pair[0].best_move(), pair[1].best_move());
+            assert!(pair[0].score >= pair[1].score);
+        }
+        assert_eq!(result.best_move(), last[0].best_move());
+
+        // asking for more lines than there are moves gives one line per move
+        let board = Board::from_fen("7k/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let mut lines = Vec::new();
+        Searcher::new(
+            board,
+            SearchLimits {
+                depth: Some(1),
+                ..Default::default()
+            },
+        )
+        .with_multi_pv(10)
+        .run(|line| lines.push(line.clone()));
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn threads_agree_on_a_mate() {
+        // Qxf7# is mate in one
+        let board =
+            Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4")
+                .unwrap();
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..Default::default()
+        };
+        let mut last_report_nodes = 0;
+        let result = Searcher::new(board.clone(), limits)
+            .with_threads(4)
+            .run(|report| last_report_nodes = report.nodes);
+        assert_eq!(result.best_move(), board.parse_move("h5f7"));
+        assert_eq!(result.score, MATE - 1);
+        // the final count includes everything the helpers searched
+        assert!(result.nodes >= last_report_nodes);
+    }
+
+    #[test]
+    fn restricted_root_moves() {
+        // Qxf7# is the best move, but only when it is allowed
+        let board =
+            Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4")
+                .unwrap();
+        let mate = board.parse_move("h5f7");
+        let limits = SearchLimits {
+            depth: Some(3),
+            exclude_moves: vec![mate.unwrap()],
+            ..Default::default()
+        };
+        let result = Searcher::new(board.clone(), limits).run(|_| {});
+        assert!(result.best_move().is_some());
+        assert_ne!(result.best_move(), mate);
+
+        let only = board.parse_move("a2a3").unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            search_moves: vec![only, mate.unwrap()],
+            exclude_moves: vec![mate.unwrap()],
+            ..Default::default()
+        };
+        let result = Searcher::new(board.clone(), limits).run(|_| {});
+        assert_eq!(result.best_move(), Some(only));
+
+        // excluding the only allowed move leaves nothing to search, not a stalemate
+        let limits = SearchLimits {
+            depth: Some(3),
+            search_moves: vec![mate.unwrap()],
+            exclude_moves: vec![mate.unwrap()],
+            ..Default::default()
+        };
+        let mut reports = 0;
+        let result = Searcher::new(board, limits).run(|_| reports += 1);
+        assert_eq!(reports, 0);
+        assert_eq!(result.best_move(), None);
+        assert_eq!(result.depth, 0);
+    }
+}
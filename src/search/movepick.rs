@@ -0,0 +1,368 @@
+use super::MAX_PLY;
+use crate::{
+    board::{movegen::GenKind, Board, Color, Piece},
+    eval::PIECE_VALUES,
+    moves::Move,
+};
+
+/// History scores are kept within plus or minus this value
+const MAX_HISTORY: i32 = 16_384;
+
+/// Move ordering statistics gathered while searching
+pub struct OrderingTables {
+    /// Two quiet moves per ply that recently caused a beta cutoff
+    killers: [[Option<Move>; 2]; MAX_PLY + 1],
+    /// How well quiet moves did in earlier cutoffs, indexed by `[color][from][to]`
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    /// The quiet move that last refuted a move, indexed by `[color][piece][to]` of the refuted move
+    counter_moves: Box<[[[Option<Move>; 64]; 6]; 2]>,
+}
+
+impl OrderingTables {
+    pub fn new() -> Self {
+        Self {
+            killers: [[None; 2]; MAX_PLY + 1],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            counter_moves: Box::new([[[None; 64]; 6]; 2]),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers[ply]
+    }
+
+    /// Returns the counter move to `previous`, the move that led to `board`
+    pub fn counter_move(&self, board: &Board, previous: Option<Move>) -> Option<Move> {
+        let previous = previous?;
+        let (piece, color) = board.piece_at(previous.to)?;
+        self.counter_moves[color as usize][piece as usize][previous.to as usize]
+    }
+
+    pub fn history(&self, color: Color, mv: Move) -> i32 {
+        self.history[color as usize][mv.from as usize][mv.to as usize]
+    }
+
+    /// Rewards the quiet move `best` for causing a beta cutoff at `ply`, and punishes the other
+    /// quiet moves in `tried`, which holds every quiet move searched at the node including `best`
+    ///
+    /// `board` is the position the moves were played from and `previous` the move leading to it.
+    pub fn update_quiet(
+        &mut self,
+        board: &Board,
+        ply: usize,
+        depth: u8,
+        best: Move,
+        previous: Option<Move>,
+        tried: &[Move],
+    ) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(best) {
+            killers[1] = killers[0];
+            killers[0] = Some(best);
+        }
+
+        if let Some(previous) = previous
+            && let Some((piece, color)) = board.piece_at(previous.to)
+        {
+            self.counter_moves[color as usize][piece as usize][previous.to as usize] = Some(best);
+        }
+
+        let color = board.side_to_move() as usize;
+        let bonus = (depth as i32 * depth as i32).min(MAX_HISTORY);
+        for mv in tried {
+            let penalty = if *mv == best { bonus } else { -bonus };
+            let entry = &mut self.history[color][mv.from as usize][mv.to as usize];
+            // scale the update down as the entry approaches the limit, so scores never leave it
+            *entry += penalty - *entry * penalty.abs() / MAX_HISTORY;
+        }
+    }
+}
+
+impl Default for OrderingTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    HashMove,
+    GenCaptures,
+    GoodCaptures,
+    FirstKiller,
+    SecondKiller,
+    CounterMove,
+    GenQuiets,
+    Quiets,
+    BadCaptures,
+    GenEvasions,
+    Evasions,
+    Done,
+}
+
+/// Hands out the pseudo-legal moves of a position one at a time, best guesses first
+///
+/// Moves are generated in stages: the hash move, captures that don't lose material by static exchange evaluation
+/// ordered by MVV-LVA, the killer moves, the counter move, quiet moves ordered by history, and
+/// finally the losing captures. Each stage is only generated once the previous one runs out,
+/// so nodes that cut off early never generate quiet moves.
+///
+/// In check, [MovePicker::evasions] hands out the evasions instead, captures first.
+pub struct MovePicker {
+    stage: Stage,
+    hash_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    counter_move: Option<Move>,
+    captures_only: bool,
+    /// Moves of the current stage with their ordering scores
+    moves: Vec<(Move, i32)>,
+    bad_captures: Vec<Move>,
+}
+
+impl MovePicker {
+    pub fn new(
+        hash_move: Option<Move>,
+        killers: [Option<Move>; 2],
+        counter_move: Option<Move>,
+    ) -> Self {
+        Self {
+            stage: Stage::HashMove,
+            hash_move,
+            killers,
+            counter_move,
+            captures_only: false,
+            moves: Vec::new(),
+            bad_captures: Vec::new(),
+        }
+    }
+
+    /// Returns a picker that only hands out captures and queen promotions
+    pub fn captures() -> Self {
+        Self {
+            stage: Stage::GenCaptures,
+            captures_only: true,
+            ..Self::new(None, [None; 2], None)
+        }
+    }
+
+    /// Returns a picker that only hands out the moves that might get the king out of check,
+    /// captures and promotions by MVV-LVA followed by quiet moves by history
+    ///
+    /// Only valid while the side to move is in check.
+    pub fn evasions() -> Self {
+        Self {
+            stage: Stage::GenEvasions,
+            ..Self::new(None, [None; 2], None)
+        }
+    }
+
+    pub fn next(&mut self, board: &Board, tables: &OrderingTables) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::GenCaptures;
+                    if let Some(mv) = self.hash_move.filter(|mv| board.is_pseudo_legal(*mv)) {
+                        return Some(mv);
+                    }
+                }
+                Stage::GenCaptures => {
+                    let mut captures = Vec::new();
+                    board.generate(GenKind::Captures, &mut captures);
+                    self.moves = captures
+                        .into_iter()
+                        .map(|mv| (mv, mvv_lva(board, mv)))
+                        .collect();
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    while let Some(mv) = self.pick_best() {
+                        if Some(mv) == self.hash_move {
+                            continue;
+                        }
+                        if !board.see_ge(mv, 0) {
+                            self.bad_captures.push(mv);
+                            continue;
+                        }
+                        return Some(mv);
+                    }
+                    self.stage = if self.captures_only {
+                        Stage::BadCaptures
+                    } else {
+                        Stage::FirstKiller
+                    };
+                }
+                Stage::FirstKiller | Stage::SecondKiller => {
+                    let killer = if self.stage == Stage::FirstKiller {
+                        self.stage = Stage::SecondKiller;
+                        self.killers[0]
+                    } else {
+                        self.stage = Stage::CounterMove;
+                        self.killers[1]
+                    };
+                    if let Some(mv) = killer.filter(|mv| self.is_new_quiet(board, *mv)) {
+                        return Some(mv);
+                    }
+                }
+                Stage::CounterMove => {
+                    self.stage = Stage::GenQuiets;
+                    if let Some(mv) = self
+                        .counter_move
+                        .filter(|mv| !self.killers.contains(&Some(*mv)))
+                        .filter(|mv| self.is_new_quiet(board, *mv))
+                    {
+                        return Some(mv);
+                    }
+                }
+                Stage::GenQuiets => {
+                    let mut quiets = Vec::new();
+                    board.generate(GenKind::Quiets, &mut quiets);
+                    let color = board.side_to_move();
+                    self.moves = quiets
+                        .into_iter()
+                        .map(|mv| (mv, tables.history(color, mv)))
+                        .collect();
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    while let Some(mv) = self.pick_best() {
+                        if Some(mv) == self.hash_move
+                            || self.killers.contains(&Some(mv))
+                            || Some(mv) == self.counter_move
+                        {
+                            continue;
+                        }
+                        return Some(mv);
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    if self.bad_captures.is_empty() {
+                        self.stage = Stage::Done;
+                    } else {
+                        return Some(self.bad_captures.remove(0));
+                    }
+                }
+                Stage::GenEvasions => {
+                    let mut evasions = Vec::new();
+                    board.generate(GenKind::Evasions, &mut evasions);
+                    let color = board.side_to_move();
+                    self.moves = evasions
+                        .into_iter()
+                        .map(|mv| {
+                            // MVV-LVA scores are positive, so this puts them above any history
+                            let score = if board.is_capture(mv) || mv.promotion.is_some() {
+                                MAX_HISTORY + mvv_lva(board, mv)
+                            } else {
+                                tables.history(color, mv)
+                            };
+                            (mv, score)
+                        })
+                        .collect();
+                    self.stage = Stage::Evasions;
+                }
+                Stage::Evasions => {
+                    if let Some(mv) = self.pick_best() {
+                        return Some(mv);
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Returns true if `mv` is a quiet move in `board` that the hash move stage didn't already return
+    fn is_new_quiet(&self, board: &Board, mv: Move) -> bool {
+        Some(mv) != self.hash_move && !board.is_capture(mv) && board.is_pseudo_legal(mv)
+    }
+
+    /// Removes and returns the highest scored move of the current stage
+    fn pick_best(&mut self) -> Option<Move> {
+        let (index, _) = self
+            .moves
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (_, score))| *score)?;
+        Some(self.moves.swap_remove(index).0)
+    }
+}
+
+/// Most valuable victim, least valuable attacker: prefer taking big pieces with small ones
+fn mvv_lva(board: &Board, mv: Move) -> i32 {
+    let victim = board
+        .piece_at(mv.to)
+        .map_or(Piece::Pawn, |(piece, _)| piece);
+    let attacker = board
+        .piece_at(mv.from)
+        .map_or(Piece::Pawn, |(piece, _)| piece);
+    let promotion = mv
+        .promotion
+        .map_or(0, |promotion| PIECE_VALUES[promotion.piece() as usize]);
+    10 * PIECE_VALUES[victim as usize] - PIECE_VALUES[attacker as usize] + promotion
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::bitboard::Square;
+
+    fn pick_all(board: &Board, mut picker: MovePicker) -> Vec<Move> {
+        let tables = OrderingTables::new();
+        let mut moves = Vec::new();
+        while let Some(mv) = picker.next(board, &tables) {
+            moves.push(mv);
+        }
+        moves
+    }
+
+    /// Checks that `picked` holds each move of `kind` exactly once
+    fn assert_complete(board: &Board, kind: GenKind, picked: &[Move]) {
+        let mut expected = Vec::new();
+        board.generate(kind, &mut expected);
+        let mut picked = picked.to_vec();
+        expected.sort_by_key(Move::lan_str);
+        picked.sort_by_key(Move::lan_str);
+        assert_eq!(picked, expected);
+    }
+
+    #[test]
+    fn stage_order() {
+        // Nxb5 wins a rook, Nxd5 and Qxd5 lose material to exd5
+        let board = Board::from_fen("4k3/8/4p3/1r1p4/8/2N5/8/3QK3 w - - 0 1").unwrap();
+        let mv = |lan| board.parse_move(lan).unwrap();
+        let killers = [Some(mv("d1d2")), Some(mv("c3e4"))];
+        let picker = MovePicker::new(Some(mv("e1f2")), killers, Some(mv("d1a4")));
+        let moves = pick_all(&board, picker);
+
+        let expected_start = ["e1f2", "c3b5", "d1d2", "c3e4", "d1a4"].map(mv);
+        assert_eq!(moves[..5], expected_start);
+        assert_eq!(moves[moves.len() - 2..], [mv("c3d5"), mv("d1d5")]);
+        assert_complete(&board, GenKind::All, &moves);
+
+        // killers that are captures or not pseudo-legal are skipped, and nothing repeats the
+        // hash move
+        let killers = [
+            Some(mv("c3b5")),
+            Some(Move::new(Square::H1, Square::H8, None)),
+        ];
+        let picker = MovePicker::new(Some(mv("c3b5")), killers, killers[1]);
+        let moves = pick_all(&board, picker);
+        assert_eq!(moves[0], mv("c3b5"));
+        assert_complete(&board, GenKind::All, &moves);
+    }
+
+    #[test]
+    fn evasions_start_with_captures() {
+        // the rook on e2 checks, taking it with the queen or the king beats moving away
+        let board = Board::from_fen("4k3/8/8/8/8/8/4r3/3QK3 w - - 0 1").unwrap();
+        let moves = pick_all(&board, MovePicker::evasions());
+        assert!(moves.len() > 2);
+        assert!(moves[..2].iter().all(|mv| board.is_capture(*mv)));
+        assert!(moves[2..].iter().all(|mv| !board.is_capture(*mv)));
+        assert_complete(&board, GenKind::Evasions, &moves);
+    }
+}
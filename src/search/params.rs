@@ -0,0 +1,147 @@
+use super::MAX_PLY;
+
+/// Most moves at a node the reduction table distinguishes, later moves share the last entry
+const MAX_MOVES: usize = 64;
+
+/// Switches and margins of the selective search, so that each technique can be tested and
+/// tuned on its own
+///
+/// Depths are in plies and margins in centipawns.
+#[derive(Clone, Debug)]
+pub struct SearchParams {
+    /// Skip the own move and prune if the opponent still can't reach beta
+    pub null_move: bool,
+    pub null_move_min_depth: u8,
+    /// The null move search is this much shallower, plus a ply per
+    /// [null_move_depth_divisor][Self::null_move_depth_divisor] plies of depth
+    pub null_move_reduction: u8,
+    pub null_move_depth_divisor: u8,
+    /// Search late quiet moves with less depth, re-searching those that raise alpha
+    pub late_move_reductions: bool,
+    pub lmr_min_depth: u8,
+    /// Reductions are `base + ln(depth) * ln(move number) / divisor`
+    pub lmr_base: f64,
+    pub lmr_divisor: f64,
+    /// Return the static evaluation when it beats beta by a depth dependent margin
+    pub reverse_futility: bool,
+    pub reverse_futility_max_depth: u8,
+    /// Per ply of depth
+    pub reverse_futility_margin: i32,
+    /// Skip quiet moves when the static evaluation is too far below alpha to catch up
+    pub futility: bool,
+    pub futility_max_depth: u8,
+    pub futility_base: i32,
+    /// Per ply of depth, added to [futility_base][Self::futility_base]
+    pub futility_margin: i32,
+    /// Drop into quiescence search when the static evaluation is hopelessly below alpha
+    pub razoring: bool,
+    pub razoring_max_depth: u8,
+    /// Per ply of depth
+    pub razoring_margin: i32,
+    /// Stop trying quiet moves after enough of them failed at low depth
+    pub late_move_pruning: bool,
+    pub late_move_pruning_max_depth: u8,
+    /// Quiet moves tried before pruning are `base + depth²`
+    pub late_move_pruning_base: usize,
+    /// Search positions in check a ply deeper
+    pub check_extension: bool,
+    /// Search the hash move a ply deeper when every other move fails well below its score
+    pub singular_extension: bool,
+    pub singular_min_depth: u8,
+    /// Per ply of depth, how far below the hash move's score the others have to stay
+    pub singular_margin: i32,
+}
+
+impl SearchParams {
+    pub const DEFAULT: SearchParams = SearchParams {
+        null_move: true,
+        null_move_min_depth: 3,
+        null_move_reduction: 3,
+        null_move_depth_divisor: 6,
+        late_move_reductions: true,
+        lmr_min_depth: 3,
+        lmr_base: 0.75,
+        lmr_divisor: 2.25,
+        reverse_futility: true,
+        reverse_futility_max_depth: 7,
+        reverse_futility_margin: 80,
+        futility: true,
+        futility_max_depth: 6,
+        futility_base: 80,
+        futility_margin: 90,
+        razoring: true,
+        razoring_max_depth: 3,
+        razoring_margin: 250,
+        late_move_pruning: true,
+        late_move_pruning_max_depth: 6,
+        late_move_pruning_base: 3,
+        check_extension: true,
+        singular_extension: true,
+        singular_min_depth: 8,
+        singular_margin: 2,
+    };
+
+    /// Precomputes the late move reductions of these parameters
+    pub fn reductions(&self) -> Reductions {
+        let mut table = vec![[0; MAX_MOVES]; MAX_PLY + 1];
+        for (depth, row) in table.iter_mut().enumerate().skip(1) {
+            for (moves, reduction) in row.iter_mut().enumerate().skip(1) {
+                let r =
+                    self.lmr_base + (depth as f64).ln() * (moves as f64).ln() / self.lmr_divisor;
+                *reduction = r.max(0.0) as u8;
+            }
+        }
+        Reductions(table)
+    }
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Late move reductions precomputed from [SearchParams], indexed by depth and move number
+#[derive(Clone, Debug)]
+pub struct Reductions(Vec<[u8; MAX_MOVES]>);
+
+impl Reductions {
+    /// Returns how many plies less the `moves`th move at a node of `depth` is searched
+    pub fn get(&self, depth: u8, moves: usize) -> u8 {
+        self.0[(depth as usize).min(MAX_PLY)][moves.min(MAX_MOVES - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reductions() {
+        let reductions = SearchParams::DEFAULT.reductions();
+        // ln(1) is 0, so the first move and depth 1 only get the rounded down base
+        assert_eq!(reductions.get(1, 40), 0);
+        assert_eq!(reductions.get(20, 1), 0);
+        assert_eq!(reductions.get(3, 3), 1);
+        assert_eq!(reductions.get(10, 20), 3);
+
+        // deeper and later means more reduction
+        for depth in 1..MAX_PLY as u8 {
+            for moves in 1..MAX_MOVES {
+                assert!(reductions.get(depth, moves) <= reductions.get(depth + 1, moves));
+                assert!(reductions.get(depth, moves) <= reductions.get(depth, moves + 1));
+            }
+        }
+        // out of range lookups share the last entries
+        assert_eq!(
+            reductions.get(u8::MAX, 500),
+            reductions.get(MAX_PLY as u8, MAX_MOVES - 1)
+        );
+
+        let params = SearchParams {
+            lmr_base: 2.0,
+            ..SearchParams::DEFAULT
+        };
+        assert_eq!(params.reductions().get(1, 1), 2);
+    }
+}
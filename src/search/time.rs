@@ -0,0 +1,211 @@
+use std::time::{Duration, Instant};
+
+use crate::moves::Move;
+
+/// Moves assumed to be left in the game when the time control doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 25;
+/// Most of the remaining time a single move may use, in percent
+const MAX_USAGE_PERCENT: u32 = 60;
+/// How far past the soft limit the hard limit lies
+const HARD_FACTOR: u32 = 4;
+/// Iterations in a row with the same best move after which it is considered stable
+const STABLE_ITERATIONS: u32 = 6;
+/// A score this much below the last iteration's counts as a drop, in centipawns
+const SCORE_DROP: i32 = 30;
+
+/// The clock situation of the side to move
+#[derive(Clone, Debug, Default)]
+pub struct TimeControl {
+    /// Time left on the clock
+    pub time: Duration,
+    /// Time added after every move
+    pub increment: Duration,
+    /// Moves until the next time control, or `None` if the rest of the game has to be played
+    pub moves_to_go: Option<u32>,
+    /// Time lost per move to communication with the GUI, subtracted up front
+    pub overhead: Duration,
+}
+
+/// Decides when to stop iterating and when the search must be aborted
+///
+/// The soft limit is checked between iterations and scaled by how settled the search is,
+/// the hard limit aborts a running iteration so the clock never runs out.
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+    start: Instant,
+    soft: Duration,
+    hard: Duration,
+    /// Whether the soft limit adapts to the search, not for a fixed time per move
+    adaptive: bool,
+    best_move: Option<Move>,
+    /// Iterations in a row the best move stayed the same
+    stability: u32,
+    last_score: Option<i32>,
+    /// Multiplier of the soft limit in percent, set after every iteration
+    scale: u32,
+}
+
+impl TimeManager {
+    /// Budgets a move under `control`, the clock started at `start`
+    pub fn new(start: Instant, control: &TimeControl) -> Self {
+        let usable = control.time.saturating_sub(control.overhead);
+        let moves_to_go = control
+            .moves_to_go
+            .unwrap_or(DEFAULT_MOVES_TO_GO)
+            .clamp(1, DEFAULT_MOVES_TO_GO * 2);
+        let max = usable * MAX_USAGE_PERCENT / 100;
+
+        // the increment only helps if it arrives before the clock runs out
+        let soft = (usable / moves_to_go + control.increment * 3 / 4).min(max);
+        let hard = if moves_to_go == 1 {
+            max
+        } else {
+            (soft * HARD_FACTOR).min(max)
+        };
+        Self {
+            start,
+            soft,
+            hard,
+            adaptive: true,
+            best_move: None,
+            stability: 0,
+            last_score: None,
+            scale: 100,
+        }
+    }
+
+    /// Spends exactly `move_time` on the move, minus `overhead`
+    pub fn fixed(start: Instant, move_time: Duration, overhead: Duration) -> Self {
+        let limit = move_time.saturating_sub(overhead);
+        Self {
+            soft: limit,
+            hard: limit,
+            adaptive: false,
+            ..Self::new(start, &TimeControl::default())
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Returns true once the search has to stop, even in the middle of an iteration
+    pub fn hard_limit_reached(&self) -> bool {
+        self.elapsed() >= self.hard
+    }
+
+    /// Returns true once the soft limit is past, a good moment to stop has likely been missed
+    pub fn soft_limit_reached(&self) -> bool {
+        self.elapsed() >= self.soft_limit()
+    }
+
+    /// Updates the soft limit after an iteration finished with `best_move` and `score`, and
+    /// returns true if no further iteration should be started
+    ///
+    /// `best_move_effort` is the share of the iteration's nodes spent on the best move,
+    /// between 0 and 1.
+    pub fn iteration_done(&mut self, best_move: Move, score: i32, best_move_effort: f64) -> bool {
+        if !self.adaptive {
+            return self.elapsed() >= self.soft;
+        }
+
+        if self.best_move == Some(best_move) {
+            self.stability += 1;
+        } else {
+            self.stability = 0;
+        }
+        self.best_move = Some(best_move);
+
+        // think longer while the best move keeps changing, shorter once it is settled
+        let mut scale = match self.stability {
+            0 => 140,
+            1 => 120,
+            n if n >= STABLE_ITERATIONS => 70,
+            _ => 100,
+        };
+        // and longer when the score just dropped, there may be a way out
+        if self
+            .last_score
+            .is_some_and(|last| score < last - SCORE_DROP)
+        {
+            scale = scale * 3 / 2;
+        }
+        // a move that takes almost all the effort is clearly better than the others
+        if best_move_effort > 0.9 {
+            scale = scale * 3 / 5;
+        }
+        self.last_score = Some(score);
+        self.scale = scale;
+
+        self.soft_limit_reached()
+    }
+
+    /// Returns the current soft limit, scaled by the last iteration and never past the hard one
+    pub fn soft_limit(&self) -> Duration {
+        (self.soft * self.scale / 100).min(self.hard)
+    }
+
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::bitboard::Square;
+
+    fn control(time_ms: u64, increment_ms: u64, moves_to_go: Option<u32>) -> TimeControl {
+        TimeControl {
+            time: Duration::from_millis(time_ms),
+            increment: Duration::from_millis(increment_ms),
+            moves_to_go,
+            overhead: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn limits_stay_within_the_clock() {
+        let controls = [
+            control(60_000, 0, None),
+            control(1_000, 10, None),
+            control(50, 10, None),
+            control(5, 10, None),
+            control(10_000, 0, Some(1)),
+            control(10_000, 5_000, Some(3)),
+        ];
+        for control in controls {
+            let manager = TimeManager::new(Instant::now(), &control);
+            let usable = control.time.saturating_sub(control.overhead);
+            assert!(manager.soft_limit() <= manager.hard_limit(), "{control:?}");
+            assert!(manager.hard_limit() <= usable, "{control:?}");
+        }
+    }
+
+    #[test]
+    fn stability_shortens_the_search() {
+        let mut manager = TimeManager::new(Instant::now(), &control(60_000, 0, None));
+        let mv = Move::new(Square::E4, Square::E2, None);
+        let other = Move::new(Square::D4, Square::D2, None);
+
+        manager.iteration_done(mv, 0, 0.5);
+        let unstable = manager.soft_limit();
+        for _ in 0..STABLE_ITERATIONS {
+            manager.iteration_done(mv, 0, 0.5);
+        }
+        let stable = manager.soft_limit();
+        assert!(stable < unstable);
+
+        manager.iteration_done(other, -100, 0.5);
+        assert!(manager.soft_limit() > unstable);
+    }
+
+    #[test]
+    fn fixed_time_per_move() {
+        let start = Instant::now();
+        let mut manager =
+            TimeManager::fixed(start, Duration::from_millis(500), Duration::from_millis(20));
+        assert_eq!(manager.hard_limit(), Duration::from_millis(480));
+        assert!(!manager.iteration_done(Move::new(Square::E4, Square::E2, None), 0, 1.0));
+    }
+}
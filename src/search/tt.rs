@@ -0,0 +1,387 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use super::{MATE, MAX_PLY};
+use crate::{
+    board::bitboard::Square,
+    moves::{Move, Promotion},
+};
+
+const SLOTS_PER_BUCKET: usize = 4;
+/// Number of distinct ages before the counter wraps around
+const AGE_CYCLE: u8 = 64;
+
+/// How a stored score relates to the exact score of the position
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact
+    #[default]
+    Exact,
+    /// The search failed high, the exact score is at least the stored one
+    Lower,
+    /// The search failed low, the exact score is at most the stored one
+    Upper,
+}
+
+/// What a previous search learned about a position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TtEntry {
+    pub best_move: Option<Move>,
+    /// Score from the side to move's point of view, mate scores are relative to the probing ply
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+/// One entry, the key is stored xored with the data so that a slot torn by two threads
+/// writing at once fails verification instead of returning mixed up data
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+#[derive(Default)]
+#[repr(align(64))]
+struct Bucket {
+    slots: [Slot; SLOTS_PER_BUCKET],
+}
+
+/// A hash table of search results keyed by Zobrist hash, shared between searches
+///
+/// Each bucket holds a few entries. A new entry replaces the one in its bucket with the lowest
+/// depth, where entries from older searches count as shallower the older they are. An entry of
+/// the same position is only replaced by one at least as deep, an exact one or any entry if it
+/// is from an earlier search.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    /// Incremented at the start of every search, stored entries record the age they were made at
+    age: AtomicU8,
+}
+
+impl TranspositionTable {
+    pub const DEFAULT_SIZE_MB: usize = 16;
+    pub const MIN_SIZE_MB: usize = 1;
+    pub const MAX_SIZE_MB: usize = 65536;
+
+    /// Creates a table using `size_mb` megabytes, clamped to the supported range
+    pub fn new(size_mb: usize) -> Self {
+        let size_mb = size_mb.clamp(Self::MIN_SIZE_MB, Self::MAX_SIZE_MB);
+        let count = size_mb * 1024 * 1024 / size_of::<Bucket>();
+        let mut buckets = Vec::with_capacity(count);
+        buckets.resize_with(count, Bucket::default);
+        Self {
+            buckets,
+            age: AtomicU8::new(0),
+        }
+    }
+
+    /// Removes every entry, e.g. between games
+    pub fn clear(&self) {
+        for bucket in &self.buckets {
+            for slot in &bucket.slots {
+                slot.key.store(0, Ordering::Relaxed);
+                slot.data.store(0, Ordering::Relaxed);
+            }
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a new search, so entries of earlier searches get replaced first
+    pub fn new_search(&self) {
+        let age = (self.age.load(Ordering::Relaxed) + 1) % AGE_CYCLE;
+        self.age.store(age, Ordering::Relaxed);
+    }
+
+    /// Looks up the position with Zobrist key `hash`, searched at `ply` from the root
+    pub fn probe(&self, hash: u64, ply: usize) -> Option<TtEntry> {
+        let bucket = self.bucket(hash);
+        bucket.slots.iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            let key = slot.key.load(Ordering::Relaxed) ^ data;
+            if key != hash {
+                return None;
+            }
+            let mut entry = unpack(data)?.0;
+            entry.score = score_from_tt(entry.score, ply);
+            Some(entry)
+        })
+    }
+
+    /// Stores the result of searching the position with Zobrist key `hash` at `ply` from the root
+    pub fn store(&self, hash: u64, ply: usize, entry: TtEntry) {
+        let age = self.age.load(Ordering::Relaxed);
+        let bucket = self.bucket(hash);
+
+        let mut victim = &bucket.slots[0];
+        let mut victim_worth = i32::MAX;
+        for slot in &bucket.slots {
+            let data = slot.data.load(Ordering::Relaxed);
+            let key = slot.key.load(Ordering::Relaxed) ^ data;
+            let Some((stored, stored_age)) = unpack(data) else {
+                // empty slot
+                if victim_worth > i32::MIN {
+                    victim = slot;
+                    victim_worth = i32::MIN;
+                }
+                continue;
+            };
+            if key == hash {
+                // same position, a shallower bound of the current search is worth less than
+                // what is stored, e.g. a quiescence result over a main search one
+                if entry.depth >= stored.depth || entry.bound == Bound::Exact || stored_age != age {
+                    // keep the old move if the new search didn't find one
+                    let entry = TtEntry {
+                        best_move: entry.best_move.or(stored.best_move),
+                        ..entry
+                    };
+                    write(slot, hash, ply, entry, age);
+                }
+                return;
+            }
+            let age_distance = (AGE_CYCLE + age - stored_age) % AGE_CYCLE;
+            let worth = stored.depth as i32 - 8 * age_distance as i32;
+            if worth < victim_worth {
+                victim = slot;
+                victim_worth = worth;
+            }
+        }
+        write(victim, hash, ply, entry, age);
+    }
+
+    /// Returns how full the table is in permille, estimated from entries of the current search
+    pub fn hashfull(&self) -> u32 {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = self.buckets.len().min(1000 / SLOTS_PER_BUCKET);
+        let used = self.buckets[..sample]
+            .iter()
+            .flat_map(|bucket| &bucket.slots)
+            .filter(|slot| {
+                unpack(slot.data.load(Ordering::Relaxed))
+                    .is_some_and(|(_, entry_age)| entry_age == age)
+            })
+            .count();
+        (used * 1000 / (sample * SLOTS_PER_BUCKET)) as u32
+    }
+
+    fn bucket(&self, hash: u64) -> &Bucket {
+        // maps the key onto the table without a modulo, using the high bits of the product
+        let index = ((hash as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[index]
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SIZE_MB)
+    }
+}
+
+fn write(slot: &Slot, hash: u64, ply: usize, entry: TtEntry, age: u8) {
+    let entry = TtEntry {
+        score: score_to_tt(entry.score, ply),
+        ..entry
+    };
+    let data = pack(entry, age);
+    slot.key.store(hash ^ data, Ordering::Relaxed);
+    slot.data.store(data, Ordering::Relaxed);
+}
+
+/// Mate scores are stored relative to the stored position rather than the root,
+/// so they stay correct when the position is reached at a different ply
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+// Data layout, from the least significant bit:
+// 16 bits move, 16 bits score, 8 bits depth, 2 bits bound (0 marks an empty slot), 6 bits age
+
+fn pack(entry: TtEntry, age: u8) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    encode_move(entry.best_move) as u64
+        | (entry.score as i16 as u16 as u64) << 16
+        | (entry.depth as u64) << 32
+        | bound << 40
+        | (age as u64) << 42
+}
+
+/// Returns the entry and its age, or `None` for an empty slot
+fn unpack(data: u64) -> Option<(TtEntry, u8)> {
+    let bound = match (data >> 40) & 0b11 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+    let entry = TtEntry {
+        best_move: decode_move(data as u16),
+        score: (data >> 16) as u16 as i16 as i32,
+        depth: (data >> 32) as u8,
+        bound,
+    };
+    Some((entry, (data >> 42) as u8 % AGE_CYCLE))
+}
+
+/// 6 bits origin, 6 bits destination and 3 bits promotion, no move is encoded as 0
+fn encode_move(mv: Option<Move>) -> u16 {
+    let Some(mv) = mv else {
+        return 0;
+    };
+    let promotion = match mv.promotion {
+        None => 0,
+        Some(Promotion::Queen) => 1,
+        Some(Promotion::Rook) => 2,
+        Some(Promotion::Bishop) => 3,
+        Some(Promotion::Knight) => 4,
+    };
+    mv.from as u16 | (mv.to as u16) << 6 | promotion << 12
+}
+
+fn decode_move(bits: u16) -> Option<Move> {
+    if bits == 0 {
+        return None;
+    }
+    let promotion = match bits >> 12 {
+        1 => Some(Promotion::Queen),
+        2 => Some(Promotion::Rook),
+        3 => Some(Promotion::Bishop),
+        4 => Some(Promotion::Knight),
+        _ => None,
+    };
+    let from = Square::from((bits & 0x3F) as u8);
+    let to = Square::from((bits >> 6 & 0x3F) as u8);
+    Some(Move::new(to, from, promotion))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let mv = Move::new(
+            Square::from(63u8),
+            Square::from(54u8),
+            Some(Promotion::Knight),
+        );
+        let entry = TtEntry {
+            best_move: Some(mv),
+            score: -1234,
+            depth: 7,
+            bound: Bound::Lower,
+        };
+        tt.store(0xDEAD_BEEF, 3, entry);
+        assert_eq!(tt.probe(0xDEAD_BEEF, 3), Some(entry));
+        assert_eq!(tt.probe(0xDEAD_BEEF ^ 1, 3), None);
+
+        // a later store without a move keeps the old one
+        tt.store(
+            0xDEAD_BEEF,
+            3,
+            TtEntry {
+                best_move: None,
+                ..entry
+            },
+        );
+        assert_eq!(tt.probe(0xDEAD_BEEF, 3).unwrap().best_move, Some(mv));
+
+        tt.clear();
+        assert_eq!(tt.probe(0xDEAD_BEEF, 3), None);
+    }
+
+    #[test]
+    fn same_position_replacement() {
+        let tt = TranspositionTable::new(1);
+        let mv = Move::new(Square::E4, Square::E2, None);
+        let deep = TtEntry {
+            best_move: Some(mv),
+            score: 50,
+            depth: 6,
+            bound: Bound::Lower,
+        };
+        tt.store(7, 0, deep);
+
+        // a quiescence bound doesn't overwrite the deeper result
+        let shallow = TtEntry {
+            best_move: None,
+            score: -20,
+            depth: 0,
+            bound: Bound::Upper,
+        };
+        tt.store(7, 0, shallow);
+        assert_eq!(tt.probe(7, 0), Some(deep));
+
+        // an exact score does, keeping the move
+        let exact = TtEntry {
+            bound: Bound::Exact,
+            ..shallow
+        };
+        tt.store(7, 0, exact);
+        assert_eq!(
+            tt.probe(7, 0),
+            Some(TtEntry {
+                best_move: Some(mv),
+                ..exact
+            })
+        );
+
+        // and so does anything once the stored entry is from an earlier search
+        tt.store(7, 0, deep);
+        tt.new_search();
+        tt.store(7, 0, shallow);
+        assert_eq!(tt.probe(7, 0).unwrap().score, shallow.score);
+    }
+
+    #[test]
+    fn mate_scores_follow_ply() {
+        let tt = TranspositionTable::new(1);
+        let entry = TtEntry {
+            best_move: None,
+            // mate in 5 plies from the root, found at ply 2
+            score: MATE - 5,
+            depth: 3,
+            bound: Bound::Exact,
+        };
+        tt.store(42, 2, entry);
+        // reached at ply 4 the same mate is two plies further away from the root
+        assert_eq!(tt.probe(42, 4).unwrap().score, MATE - 7);
+    }
+
+    #[test]
+    fn hashfull_counts_current_search() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+        let entry = TtEntry {
+            best_move: None,
+            score: 0,
+            depth: 1,
+            bound: Bound::Exact,
+        };
+        for hash in 0..1_000_000u64 {
+            tt.store(hash.wrapping_mul(0x9E37_79B9_7F4A_7C15), 0, entry);
+        }
+        assert!(tt.hashfull() > 900);
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+}
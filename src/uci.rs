@@ -0,0 +1,462 @@
+use std::{
+    iter::Peekable,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::Board,
+    engine::{Engine, DEFAULT_MOVE_OVERHEAD_MS, MAX_MOVE_OVERHEAD_MS, MAX_MULTI_PV, START_FEN},
+    eval::trace::trace,
+    moves::Move,
+    search::{
+        is_mate_score,
+        mate::MateReport,
+        params::SearchParams,
+        time::{TimeControl, TimeManager},
+        tt::{Bound, TranspositionTable},
+        SearchLimits, SearchReport, MATE, MAX_THREADS,
+    },
+};
+
+/// Returns the flag of a search technique in [SearchParams]
+type Switch = fn(&mut SearchParams) -> &mut bool;
+
+/// Check options switching search techniques on and off, so that each can be tested alone
+const SEARCH_SWITCHES: [(&str, Switch); 8] = [
+    ("NullMove", |params| &mut params.null_move),
+    ("LateMoveReductions", |params| {
+        &mut params.late_move_reductions
+    }),
+    ("ReverseFutility", |params| &mut params.reverse_futility),
+    ("Futility", |params| &mut params.futility),
+    ("Razoring", |params| &mut params.razoring),
+    ("LateMovePruning", |params| &mut params.late_move_pruning),
+    ("CheckExtension", |params| &mut params.check_extension),
+    ("SingularExtension", |params| &mut params.singular_extension),
+];
+
+/// Words starting a parameter of `go`, which end a list of moves
+const GO_PARAMETERS: [&str; 13] = [
+    "depth",
+    "nodes",
+    "movetime",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "infinite",
+    "mate",
+    "searchmoves",
+    "exclude",
+    "ponder",
+];
+
+/// Reads UCI commands from `input` until `quit` or the end of input
+pub fn run(engine: &mut Engine, input: impl Iterator<Item = String>) {
+    for line in input {
+        if !handle(engine, &line) {
+            break;
+        }
+    }
+    engine.stop();
+}
+
+/// Executes one command, returns false once the engine should quit
+pub fn handle(engine: &mut Engine, line: &str) -> bool {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let Some((&command, args)) = tokens.split_first() else {
+        return true;
+    };
+    match command {
+        "uci" => {
+            println!("id name chessbot {}", env!("CARGO_PKG_VERSION"));
+            println!("id author occisor2");
+            println!(
+                "option name Hash type spin default {} min {} max {}",
+                TranspositionTable::DEFAULT_SIZE_MB,
+                TranspositionTable::MIN_SIZE_MB,
+                TranspositionTable::MAX_SIZE_MB
+            );
+            println!("option name Clear Hash type button");
+            println!("option name Threads type spin default 1 min 1 max {MAX_THREADS}");
+            println!("option name Ponder type check default false");
+            println!("option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}");
+            println!("option name EvalFile type string default <empty>");
+            println!(
+                "option name Move Overhead type spin default {DEFAULT_MOVE_OVERHEAD_MS} min 0 max {MAX_MOVE_OVERHEAD_MS}"
+            );
+            let mut defaults = SearchParams::DEFAULT;
+            for (name, switch) in SEARCH_SWITCHES {
+                println!(
+                    "option name {name} type check default {}",
+                    switch(&mut defaults)
+                );
+            }
+            // the log-based reduction table's terms in hundredths
+            println!(
+                "option name LmrBase type spin default {} min 0 max 500",
+                (defaults.lmr_base * 100.0).round()
+            );
+            println!(
+                "option name LmrDivisor type spin default {} min 50 max 1000",
+                (defaults.lmr_divisor * 100.0).round()
+            );
+            println!("uciok");
+        }
+        "isready" => println!("readyok"),
+        "ucinewgame" => engine.new_game(),
+        "setoption" => set_option(engine, args),
+        "position" => {
+            engine.stop();
+            if !set_position(engine, args) {
+                println!("info string invalid position: {line}");
+            }
+        }
+        "go" => go(engine, args),
+        "stop" => engine.stop(),
+        "ponderhit" => engine.ponder_hit(),
+        "quit" => return false,
+        // not part of UCI, prints the board and its evaluation for debugging
+        "d" => print!("{}", engine.board()),
+        "eval" => print!("{}", trace(engine.board())),
+        _ => println!("info string unknown command: {line}"),
+    }
+    true
+}
+
+/// Handles `setoption name <name> [value <value>]`, names and values may contain spaces
+fn set_option(engine: &mut Engine, args: &[&str]) {
+    let Some(name_at) = args.iter().position(|&token| token == "name") else {
+        println!("info string setoption without a name");
+        return;
+    };
+    // a value before the name would be ambiguous, so only one after it counts
+    let value_at = args[name_at..]
+        .iter()
+        .position(|&token| token == "value")
+        .map(|at| name_at + at);
+    let name = args[name_at + 1..value_at.unwrap_or(args.len())].join(" ");
+    let value = value_at.map_or(String::new(), |at| args[at + 1..].join(" "));
+    if name.is_empty() {
+        println!("info string setoption without a name");
+        return;
+    }
+
+    match name.to_lowercase().as_str() {
+        "hash" => match value.parse() {
+            Ok(size_mb) => engine.set_hash_size(size_mb),
+            Err(_) => println!("info string invalid hash size: {value}"),
+        },
+        "clear hash" => engine.clear_hash(),
+        "threads" => match value.parse() {
+            Ok(threads) => engine.set_threads(threads),
+            Err(_) => println!("info string invalid thread count: {value}"),
+        },
+        // pondering is up to the GUI, the option only tells it that the engine can
+        "ponder" => {}
+        "multipv" => match value.parse() {
+            Ok(lines) => engine.set_multi_pv(lines),
+            Err(_) => println!("info string invalid multipv: {value}"),
+        },
+        "evalfile" => {
+            let path = if value == "<empty>" { "" } else { &value };
+            if let Err(err) = engine.load_network(path) {
+                println!("info string {err}");
+            }
+        }
+        "move overhead" => match value.parse::<u64>() {
+            Ok(ms) => engine.set_move_overhead(Duration::from_millis(ms.min(MAX_MOVE_OVERHEAD_MS))),
+            Err(_) => println!("info string invalid move overhead: {value}"),
+        },
+        "lmrbase" | "lmrdivisor" => match value.parse::<u32>() {
+            Ok(hundredths) => {
+                let mut params = engine.search_params().clone();
+                let value = f64::from(hundredths) / 100.0;
+                if name.eq_ignore_ascii_case("lmrbase") {
+                    params.lmr_base = value;
+                } else {
+                    params.lmr_divisor = value.max(0.5);
+                }
+                engine.set_search_params(params);
+            }
+            Err(_) => println!("info string invalid {name}: {value}"),
+        },
+        _ => match SEARCH_SWITCHES
+            .iter()
+            .find(|(switch, _)| switch.eq_ignore_ascii_case(&name))
+        {
+            Some((_, switch)) => match value.parse() {
+                Ok(on) => {
+                    let mut params = engine.search_params().clone();
+                    *switch(&mut params) = on;
+                    engine.set_search_params(params);
+                }
+                Err(_) => println!("info string invalid {name}: {value}"),
+            },
+            None => println!("info string unknown option: {name}"),
+        },
+    }
+}
+
+/// Handles `position startpos|fen <fen> [moves <move>...]`, returns false if it is invalid
+fn set_position(engine: &mut Engine, args: &[&str]) -> bool {
+    let moves_at = args
+        .iter()
+        .position(|&token| token == "moves")
+        .unwrap_or(args.len());
+    let valid = match args.first() {
+        Some(&"startpos") => engine.set_fen(START_FEN),
+        Some(&"fen") => engine.set_fen(&args[1..moves_at].join(" ")),
+        _ => false,
+    };
+    valid
+        && args
+            .iter()
+            .skip(moves_at + 1)
+            .all(|lan| engine.play(lan).is_some())
+}
+
+/// Handles `go` and starts searching
+fn go(engine: &mut Engine, args: &[&str]) {
+    let start = Instant::now();
+    let mut limits = SearchLimits::default();
+    let mut infinite = false;
+    let mut ponder = false;
+    let (mut time, mut increment) = (None, 0);
+    let mut moves_to_go = None;
+    let mut move_time = None;
+    let mut mate = None;
+    let white = engine.board().side_to_move() == crate::board::Color::White;
+
+    let mut tokens = args.iter().peekable();
+    while let Some(&token) = tokens.next() {
+        let mut value = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+        match token {
+            "depth" => limits.depth = value().map(|depth| depth.min(u8::MAX as u64) as u8),
+            "nodes" => limits.nodes = value(),
+            "movetime" => move_time = value(),
+            "wtime" | "btime" => {
+                let ms = parse_clock(tokens.next());
+                if (token == "wtime") == white {
+                    time = ms;
+                }
+            }
+            "winc" | "binc" => {
+                let ms = parse_clock(tokens.next());
+                if (token == "winc") == white {
+                    increment = ms.unwrap_or(0);
+                }
+            }
+            "movestogo" => moves_to_go = value(),
+            "infinite" => infinite = true,
+            "mate" => mate = value(),
+            "searchmoves" | "exclude" => match parse_moves(engine.board(), &mut tokens) {
+                Ok(moves) if token == "searchmoves" => limits.search_moves = moves,
+                Ok(moves) => limits.exclude_moves = moves,
+                Err(lan) => {
+                    // searching other moves than the ones asked for would answer the wrong question
+                    println!("info string invalid move in {token}: {lan}");
+                    println!("bestmove 0000");
+                    return;
+                }
+            },
+            "ponder" => ponder = true,
+            _ => {}
+        }
+    }
+    if let Some(moves) = mate {
+        engine.start_mate_search(moves.min(u8::MAX as u64) as u8, move |report| {
+            print_mate(&report, moves)
+        });
+        return;
+    }
+
+    let overhead = engine.move_overhead();
+    if let Some(ms) = move_time {
+        limits.time = Some(TimeManager::fixed(
+            start,
+            Duration::from_millis(ms),
+            overhead,
+        ));
+    } else if let Some(time) = time {
+        let control = TimeControl {
+            time: Duration::from_millis(time),
+            increment: Duration::from_millis(increment),
+            moves_to_go: moves_to_go.map(|moves| moves as u32),
+            overhead,
+        };
+        limits.time = Some(TimeManager::new(start, &control));
+    }
+
+    if ponder {
+        engine.start_ponder(limits, print_info, print_best_move);
+    } else {
+        engine.start_search(limits, infinite, print_info, print_best_move);
+    }
+}
+
+/// Parses a clock or increment in milliseconds, a clock that already ran out can show up
+/// negative and counts as 0
+fn parse_clock(ms: Option<&&str>) -> Option<u64> {
+    ms?.parse::<i64>().ok().map(|ms| ms.max(0) as u64)
+}
+
+fn print_best_move(result: SearchReport) {
+    match (result.best_move(), result.ponder_move()) {
+        (Some(best), Some(ponder)) => println!("bestmove {best} ponder {ponder}"),
+        (Some(best), None) => println!("bestmove {best}"),
+        (None, _) => println!("bestmove 0000"),
+    }
+}
+
+/// Takes the moves from the front of `tokens` up to the next `go` parameter, or returns the
+/// first one that isn't a legal move
+fn parse_moves<'a>(
+    board: &Board,
+    tokens: &mut Peekable<impl Iterator<Item = &'a &'a str>>,
+) -> Result<Vec<Move>, &'a str> {
+    let mut moves = Vec::new();
+    while let Some(&lan) = tokens.next_if(|lan| !GO_PARAMETERS.contains(lan)) {
+        moves.push(board.parse_move(lan).ok_or(lan)?);
+    }
+    Ok(moves)
+}
+
+/// Prints the result of `go mate <moves>`, the null move `0000` if there is no mate
+fn print_mate(report: &MateReport, moves: u64) {
+    let millis = report.elapsed.as_millis() as u64;
+    let Some(solution) = &report.solution else {
+        if !report.stopped {
+            println!("info string no mate in {moves}");
+        }
+        println!("bestmove 0000");
+        return;
+    };
+    let pv: Vec<String> = solution.pv.iter().map(Move::lan_str).collect();
+    println!(
+        "info depth {} score mate {} nodes {} time {millis} pv {}",
+        solution.moves as u32 * 2 - 1,
+        solution.moves,
+        report.nodes,
+        pv.join(" ")
+    );
+    for (cook, moves) in &report.cooks {
+        println!("info string cook {cook} mate {moves}");
+    }
+    match solution.pv.get(1) {
+        Some(ponder) => println!("bestmove {} ponder {ponder}", solution.pv[0]),
+        None => println!("bestmove {}", solution.pv[0]),
+    }
+}
+
+fn print_info(report: &SearchReport) {
+    let millis = report.elapsed.as_millis() as u64;
+    let nps = report.nodes * 1000 / millis.max(1);
+    let pv: Vec<String> = report.pv.iter().map(Move::lan_str).collect();
+    let bound = match report.bound {
+        Bound::Exact => "",
+        Bound::Lower => " lowerbound",
+        Bound::Upper => " upperbound",
+    };
+    println!(
+        "info depth {} seldepth {} multipv {} score {}{bound} nodes {} nps {nps} hashfull {} time {millis} pv {}",
+        report.depth,
+        report.seldepth,
+        report.multipv,
+        score_string(report.score),
+        report.nodes,
+        report.hashfull,
+        pv.join(" ")
+    );
+}
+
+/// Formats `score` as `cp <centipawns>` or `mate <moves>`, negative when getting mated
+pub fn score_string(score: i32) -> String {
+    if is_mate_score(score) {
+        let plies = MATE - score.abs();
+        let moves = (plies + 1) / 2;
+        if score > 0 {
+            format!("mate {moves}")
+        } else {
+            format!("mate -{moves}")
+        }
+    } else {
+        format!("cp {score}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores() {
+        assert_eq!(score_string(35), "cp 35");
+        assert_eq!(score_string(-120), "cp -120");
+        // mate in one is a single ply away
+        assert_eq!(score_string(MATE - 1), "mate 1");
+        assert_eq!(score_string(MATE - 3), "mate 2");
+        assert_eq!(score_string(-(MATE - 2)), "mate -1");
+    }
+
+    #[test]
+    fn positions() {
+        let mut engine = Engine::new();
+        assert!(set_position(
+            &mut engine,
+            &["startpos", "moves", "e2e4", "c7c5", "g1f3"]
+        ));
+        assert_eq!(
+            engine.board().hash(),
+            crate::board::Board::from_fen(
+                "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+            )
+            .unwrap()
+            .hash()
+        );
+        let fen = "8/8/8/8/8/8/8/K6k w - - 0 1".split(' ').collect::<Vec<_>>();
+        assert!(set_position(&mut engine, &[&["fen"], &fen[..]].concat()));
+        assert!(!set_position(&mut engine, &["startpos", "moves", "e2e5"]));
+        assert!(!set_position(&mut engine, &["fen", "not", "a", "fen"]));
+    }
+
+    #[test]
+    fn clocks() {
+        assert_eq!(parse_clock(Some(&"1500")), Some(1500));
+        assert_eq!(parse_clock(Some(&"-50")), Some(0));
+        assert_eq!(parse_clock(Some(&"soon")), None);
+        assert_eq!(parse_clock(None), None);
+    }
+
+    #[test]
+    fn options() {
+        let mut engine = Engine::new();
+        set_option(&mut engine, &["name", "Move", "Overhead", "value", "70"]);
+        assert_eq!(engine.move_overhead(), Duration::from_millis(70));
+        // malformed commands are reported instead of panicking
+        set_option(&mut engine, &["value", "5"]);
+        set_option(&mut engine, &["value", "5", "name", "Move", "Overhead"]);
+        set_option(&mut engine, &["name", "value", "5"]);
+        set_option(&mut engine, &[]);
+        assert_eq!(engine.move_overhead(), Duration::from_millis(70));
+    }
+
+    #[test]
+    fn move_lists() {
+        let board = Board::from_fen(START_FEN).unwrap();
+        let parse =
+            |args: &'static [&'static str]| parse_moves(&board, &mut args.iter().peekable());
+        let moves = |lans: &[&str]| -> Vec<Move> {
+            lans.iter()
+                .map(|lan| board.parse_move(lan).unwrap())
+                .collect()
+        };
+        assert_eq!(parse(&["e2e4", "g1f3"]), Ok(moves(&["e2e4", "g1f3"])));
+        assert_eq!(parse(&["e2e4", "depth", "3"]), Ok(moves(&["e2e4"])));
+        assert_eq!(parse(&[]), Ok(Vec::new()));
+        // an illegal move must not end the list early and leave every move allowed
+        assert_eq!(parse(&["e2e5"]), Err("e2e5"));
+        assert_eq!(parse(&["e2e4", "e2e5", "g1f3"]), Err("e2e5"));
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    sync::mpsc::{self, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::{Board, Color},
+    engine::Engine,
+    moves::Move,
+    search::{
+        is_mate_score,
+        time::{TimeControl, TimeManager},
+        tt::Bound,
+        SearchLimits, SearchReport, MATE,
+    },
+};
+
+/// Something the front end has to react to
+enum Event {
+    /// A command from the GUI
+    Command(String),
+    /// A search finished, tagged with the number of the search that produced it
+    SearchDone(u64, SearchReport),
+}
+
+/// Session state of the WinBoard protocol
+struct Xboard {
+    engine: Engine,
+    events: Sender<Event>,
+    /// Numbers searches so results of cancelled ones can be told apart
+    search_id: u64,
+    /// In force mode moves are only played, never answered
+    force: bool,
+    /// The side the engine plays
+    engine_side: Option<Color>,
+    analyzing: bool,
+    post: bool,
+    /// Moves per time control (0 for the whole game) and increment, from `level`
+    moves_per_control: u32,
+    increment: Duration,
+    /// Fixed time per move from `st`
+    move_time: Option<Duration>,
+    /// Depth limit from `sd`
+    depth: Option<u8>,
+    /// Clocks from `time` and `otim`, both start at the base time of `level`
+    own_time: Option<Duration>,
+    opponent_time: Option<Duration>,
+}
+
+/// Speaks the WinBoard protocol (CECP version 2) until `quit` or the end of `input`
+///
+/// The `xboard` command selecting this protocol has already been read.
+pub fn run(engine: Engine, input: impl Iterator<Item = String> + Send + 'static) {
+    let (events, receiver) = mpsc::channel();
+    let commands = events.clone();
+    thread::spawn(move || {
+        for line in input {
+            if commands.send(Event::Command(line)).is_err() {
+                break;
+            }
+        }
+        let _ = commands.send(Event::Command("quit".to_string()));
+    });
+
+    let mut xboard = Xboard::new(engine, events);
+    for event in receiver {
+        match event {
+            Event::Command(line) => {
+                if !xboard.handle(&line) {
+                    break;
+                }
+            }
+            Event::SearchDone(id, result) => {
+                if id == xboard.search_id && !xboard.analyzing {
+                    xboard.play_result(result);
+                }
+            }
+        }
+    }
+    xboard.engine.stop();
+}
+
+impl Xboard {
+    /// Starts a session where the engine plays black, search results are sent to `events`
+    fn new(engine: Engine, events: Sender<Event>) -> Self {
+        Self {
+            engine,
+            events,
+            search_id: 0,
+            force: false,
+            engine_side: Some(Color::Black),
+            analyzing: false,
+            post: false,
+            moves_per_control: 0,
+            increment: Duration::ZERO,
+            move_time: None,
+            depth: None,
+            own_time: None,
+            opponent_time: None,
+        }
+    }
+
+    /// Executes one command, returns false once the engine should quit
+    fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = tokens.split_first() else {
+            return true;
+        };
+        match command {
+            "xboard" | "accepted" | "rejected" | "random" | "computer" | "hard" | "easy"
+            | "name" | "rating" | "ics" | "." => {}
+            "protover" => println!("{}", features()),
+            "new" => {
+                self.cancel_search();
+                self.engine.new_game();
+                self.force = false;
+                self.engine_side = Some(Color::Black);
+                self.depth = None;
+                self.restart_analysis();
+            }
+            "setboard" => {
+                self.cancel_search();
+                if !self.engine.set_fen(&args.join(" ")) {
+                    println!("tellusererror Illegal position");
+                }
+                self.restart_analysis();
+            }
+            "usermove" => match args.first() {
+                Some(lan) => self.user_move(lan),
+                None => println!("Error (missing move): usermove"),
+            },
+            "go" => {
+                self.force = false;
+                self.set_engine_side(self.engine.board().side_to_move());
+                self.think();
+            }
+            "force" => {
+                self.cancel_search();
+                self.force = true;
+            }
+            "playother" => {
+                self.force = false;
+                self.set_engine_side(self.engine.board().side_to_move().opposite());
+            }
+            "level" => self.set_level(args),
+            "st" => {
+                if let Some(move_time) = args.first().and_then(|seconds| parse_seconds(seconds)) {
+                    self.move_time = Some(move_time);
+                }
+            }
+            "sd" => {
+                self.depth = args
+                    .first()
+                    .and_then(|depth| depth.parse::<u64>().ok())
+                    .map(|depth| depth.min(u8::MAX as u64) as u8)
+            }
+            "time" | "otim" => {
+                // a clock that already ran out can show up negative
+                let Some(centis) = args.first().and_then(|centis| centis.parse::<i64>().ok())
+                else {
+                    return true;
+                };
+                let clock = Some(Duration::from_millis(
+                    (centis.max(0) as u64).saturating_mul(10),
+                ));
+                if command == "time" {
+                    self.own_time = clock;
+                } else {
+                    self.opponent_time = clock;
+                }
+            }
+            "undo" | "remove" => {
+                self.cancel_search();
+                let count = if command == "undo" { 1 } else { 2 };
+                for _ in 0..count {
+                    self.engine.undo();
+                }
+                self.restart_analysis();
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "result" => {
+                self.cancel_search();
+                self.force = true;
+            }
+            "analyze" => {
+                self.analyzing = true;
+                self.restart_analysis();
+            }
+            "exit" => {
+                self.cancel_search();
+                self.analyzing = false;
+            }
+            // move now
+            "?" => self.engine.stop(),
+            "ping" => println!("pong {}", args.first().unwrap_or(&"")),
+            "quit" => return false,
+            _ => {
+                // without the usermove feature accepted, moves arrive on their own
+                if self.engine.board().parse_move(command).is_some() {
+                    self.user_move(command);
+                } else {
+                    println!("Error (unknown command): {command}");
+                }
+            }
+        }
+        true
+    }
+
+    /// Handles `level MPS BASE INC`, where BASE is minutes or `minutes:seconds` and INC is
+    /// seconds, ignoring it unless all three are valid
+    ///
+    /// Both clocks start at BASE until `time` and `otim` say otherwise.
+    fn set_level(&mut self, args: &[&str]) {
+        let [moves, base, increment] = args else {
+            return;
+        };
+        let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+        let base = minutes
+            .parse::<u64>()
+            .ok()
+            .zip(seconds.parse::<u64>().ok())
+            .map(|(minutes, seconds)| {
+                Duration::from_secs(minutes.saturating_mul(60).saturating_add(seconds))
+            });
+        if let (Ok(moves), Some(base), Some(increment)) =
+            (moves.parse(), base, parse_seconds(increment))
+        {
+            self.moves_per_control = moves;
+            self.increment = increment;
+            self.own_time = Some(base);
+            self.opponent_time = Some(base);
+            self.move_time = None;
+        }
+    }
+
+    /// Makes the engine play `side`, taking over the opponent's clock if that is a change
+    fn set_engine_side(&mut self, side: Color) {
+        if self.engine_side == Some(side.opposite()) {
+            std::mem::swap(&mut self.own_time, &mut self.opponent_time);
+        }
+        self.engine_side = Some(side);
+    }
+
+    fn user_move(&mut self, lan: &str) {
+        self.cancel_search();
+        if self.engine.play(lan).is_none() {
+            println!("Illegal move: {lan}");
+            return;
+        }
+        if self.analyzing {
+            self.restart_analysis();
+        } else if !self.force && self.engine_side == Some(self.engine.board().side_to_move()) {
+            self.think();
+        }
+    }
+
+    /// Starts searching for a move to play
+    fn think(&mut self) {
+        let start = Instant::now();
+        let mut limits = SearchLimits {
+            depth: self.depth,
+            ..Default::default()
+        };
+        let overhead = self.engine.move_overhead();
+        if let Some(move_time) = self.move_time {
+            limits.time = Some(TimeManager::fixed(start, move_time, overhead));
+        } else if let Some(time) = self.own_time {
+            let moves_to_go = (self.moves_per_control > 0).then(|| {
+                let played = self.engine.board().full_moves() - 1;
+                self.moves_per_control - played % self.moves_per_control
+            });
+            let control = TimeControl {
+                time,
+                increment: self.increment,
+                moves_to_go,
+                overhead,
+            };
+            limits.time = Some(TimeManager::new(start, &control));
+        }
+        self.start_search(limits, false);
+    }
+
+    /// Restarts infinite analysis of the current position, if in analyze mode
+    fn restart_analysis(&mut self) {
+        if self.analyzing {
+            self.start_search(SearchLimits::default(), true);
+        }
+    }
+
+    fn start_search(&mut self, limits: SearchLimits, infinite: bool) {
+        self.cancel_search();
+        self.search_id += 1;
+        let id = self.search_id;
+        let events = self.events.clone();
+        let post = self.post || self.analyzing;
+        self.engine.start_search(
+            limits,
+            infinite,
+            move |report| {
+                if post {
+                    print_thinking(report);
+                }
+            },
+            move |result| {
+                let _ = events.send(Event::SearchDone(id, result));
+            },
+        );
+    }
+
+    /// Stops the running search without playing its result
+    fn cancel_search(&mut self) {
+        self.search_id += 1;
+        self.engine.stop();
+    }
+
+    fn play_result(&mut self, result: SearchReport) {
+        let Some(best) = result.best_move() else {
+            // the GUI would wait for a move forever
+            println!("{}", game_result(self.engine.board()));
+            return;
+        };
+        self.engine.play_move(best);
+        println!("move {best}");
+    }
+}
+
+/// Returns the result to claim when the side to move of `board` has no legal move
+fn game_result(board: &Board) -> &'static str {
+    match (board.in_check(), board.side_to_move()) {
+        (false, _) => "1/2-1/2 {Stalemate}",
+        (true, Color::White) => "0-1 {Black mates}",
+        (true, Color::Black) => "1-0 {White mates}",
+    }
+}
+
+/// Returns the `feature` reply to `protover`
+fn features() -> String {
+    format!(
+        "feature myname=\"chessbot {}\" setboard=1 usermove=1 ping=1 analyze=1 colors=0 \
+         sigint=0 sigterm=0 variants=\"normal\" done=1",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Parses a non-negative number of seconds, possibly fractional
+fn parse_seconds(seconds: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds.parse().ok()?).ok()
+}
+
+/// Prints `ply score time nodes pv`, with the time in centiseconds
+fn print_thinking(report: &SearchReport) {
+    // the protocol has no way to mark a score as a bound
+    if report.bound != Bound::Exact {
+        return;
+    }
+    let pv: Vec<String> = report.pv.iter().map(Move::lan_str).collect();
+    println!(
+        "{} {} {} {} {}",
+        report.depth,
+        score_value(report.score),
+        report.elapsed.as_millis() / 10,
+        report.nodes,
+        pv.join(" ")
+    );
+}
+
+/// Converts `score` to the protocol's convention, where mate in `n` moves is 100000 + `n`
+fn score_value(score: i32) -> i32 {
+    if is_mate_score(score) {
+        let moves = (MATE - score.abs() + 1) / 2;
+        if score > 0 {
+            100_000 + moves
+        } else {
+            -100_000 - moves
+        }
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(commands: &[&str]) -> Xboard {
+        let (events, _) = mpsc::channel();
+        let mut xboard = Xboard::new(Engine::new(), events);
+        for command in commands {
+            assert!(xboard.handle(command), "{command}");
+        }
+        xboard
+    }
+
+    #[test]
+    fn feature_reply() {
+        let reply = features();
+        for feature in [
+            "setboard=1",
+            "usermove=1",
+            "ping=1",
+            "analyze=1",
+            "colors=0",
+        ] {
+            assert!(reply.contains(feature), "{feature}");
+        }
+        assert!(reply.ends_with("done=1"));
+    }
+
+    #[test]
+    fn results() {
+        let result = |fen| game_result(&Board::from_fen(fen).unwrap());
+        assert_eq!(
+            result("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"),
+            "1-0 {White mates}"
+        );
+        assert_eq!(
+            result("8/8/8/8/8/1k6/1q6/K7 w - - 0 1"),
+            "0-1 {Black mates}"
+        );
+        assert_eq!(
+            result("k7/8/1QK5/8/8/8/8/8 b - - 0 1"),
+            "1/2-1/2 {Stalemate}"
+        );
+    }
+
+    #[test]
+    fn clocks() {
+        let xboard = session(&["level 40 5 0", "otim 25000"]);
+        assert_eq!(xboard.moves_per_control, 40);
+        assert_eq!(xboard.own_time, Some(Duration::from_secs(300)));
+        assert_eq!(xboard.opponent_time, Some(Duration::from_secs(250)));
+
+        let xboard = session(&["level 0 2:30 1.5", "st 5", "level 0 x 1", "level 0 1 -1"]);
+        assert_eq!(xboard.own_time, Some(Duration::from_secs(150)));
+        assert_eq!(xboard.increment, Duration::from_millis(1500));
+        assert_eq!(xboard.move_time, Some(Duration::from_secs(5)));
+
+        // nonsense times are ignored and overdrawn clocks count as empty
+        let xboard = session(&["st 2", "st -1", "st nan", "st inf", "time 6000", "time -50"]);
+        assert_eq!(xboard.move_time, Some(Duration::from_secs(2)));
+        assert_eq!(xboard.own_time, Some(Duration::ZERO));
+
+        // too deep a depth limit is clamped rather than dropped
+        assert_eq!(session(&["sd 12"]).depth, Some(12));
+        assert_eq!(session(&["sd 1000"]).depth, Some(u8::MAX));
+
+        // taking over the other side takes over its clock
+        let xboard = session(&["time 1000", "otim 2000", "force", "playother"]);
+        assert_eq!(xboard.engine_side, Some(Color::Black));
+        assert_eq!(xboard.own_time, Some(Duration::from_secs(10)));
+        let xboard = session(&["time 1000", "otim 2000", "force", "e2e4", "playother"]);
+        assert_eq!(xboard.engine_side, Some(Color::White));
+        assert_eq!(xboard.own_time, Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn moves_and_undo() {
+        let start = Engine::new().board().hash();
+        let mut xboard = session(&["force", "usermove e2e4", "e7e5"]);
+        assert_eq!(xboard.engine.board().side_to_move(), Color::White);
+
+        // illegal moves leave the position alone
+        let hash = xboard.engine.board().hash();
+        xboard.handle("usermove e4e6");
+        assert_eq!(xboard.engine.board().hash(), hash);
+
+        xboard.handle("undo");
+        assert_eq!(xboard.engine.board().side_to_move(), Color::Black);
+        xboard.handle("usermove e7e5");
+        xboard.handle("remove");
+        assert_eq!(xboard.engine.board().hash(), start);
+    }
+}